@@ -0,0 +1,355 @@
+use std::{
+    error::Error,
+    fs,
+    io::Cursor,
+    path::PathBuf
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::{
+    style::Color,
+    terminal
+};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbImage};
+use reqwest::Client;
+
+use super::paths;
+use super::renderer::{Frame, Graphic, Style};
+use super::theme::ColorDepth;
+
+/*
+
+Album art next to the now playing box, the cover gets downloaded once,
+kept in the cache dir and then drawn with whatever the terminal supports
+
+*/
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ArtProtocol
+{
+    // two pixels per cell using ▀ with a truecolor foreground and background
+    HalfBlock,
+    Kitty,
+    Sixel,
+    Off
+}
+
+impl ArtProtocol
+{
+    /**
+        ALBUM_ART in the .env wins, otherwise we guess from the environment
+        the terminal sets, anything we dont recognise gets half blocks
+    **/
+    pub fn detect() -> Self
+    {
+        match std::env::var("ALBUM_ART").unwrap_or_default().to_lowercase().as_str()
+        {
+            "halfblock" | "half-block" => return ArtProtocol::HalfBlock,
+            "kitty" => return ArtProtocol::Kitty,
+            "sixel" => return ArtProtocol::Sixel,
+            "off" | "none" => return ArtProtocol::Off,
+            _ => {}
+        }
+
+        // tmux swallows the graphics escapes unless passthrough is set up
+        if std::env::var_os("TMUX").is_some() {
+            return ArtProtocol::HalfBlock;
+        }
+
+        let term = std::env::var("TERM").unwrap_or_default();
+        let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || term_program == "WezTerm"
+            || term_program == "ghostty"
+        {
+            return ArtProtocol::Kitty;
+        }
+
+        if term.contains("sixel") || term.contains("mlterm") || term.starts_with("foot") {
+            return ArtProtocol::Sixel;
+        }
+
+        ArtProtocol::HalfBlock
+    }
+}
+
+enum PreparedContent
+{
+    Pixels(RgbImage),
+    Escape(String)
+}
+
+struct PreparedArt
+{
+    cols : u16,
+    rows : u16,
+    content : PreparedContent,
+}
+
+pub struct AlbumArt
+{
+    protocol : ArtProtocol,
+    cache_dir : PathBuf,
+    url : Option<String>,
+    image : Option<DynamicImage>,
+    prepared : Option<PreparedArt>,
+}
+
+impl AlbumArt
+{
+    /**
+        Half blocks are nothing but color, with none to show the cover stays off
+    **/
+    pub fn new(color_depth : ColorDepth) -> Self
+    {
+        let protocol = match ArtProtocol::detect() {
+            ArtProtocol::HalfBlock if color_depth == ColorDepth::Monochrome => ArtProtocol::Off,
+            protocol => protocol,
+        };
+
+        AlbumArt {
+            protocol,
+            cache_dir : paths::cache_dir().join("art"),
+            url : None,
+            image : None,
+            prepared : None,
+        }
+    }
+
+    pub fn has_image(&self) -> bool
+    {
+        self.protocol != ArtProtocol::Off && self.image.is_some()
+    }
+
+    /**
+        Forgets the old cover when the url changes, returns true when the
+        new one still has to be fetched
+    **/
+    pub fn set_url(&mut self, url : Option<&str>) -> bool
+    {
+        if self.protocol == ArtProtocol::Off || url == self.url.as_deref() {
+            return false;
+        }
+
+        self.url = url.map(|u| u.to_string());
+        self.image = None;
+        self.prepared = None;
+        self.url.is_some()
+    }
+
+    /**
+        Hands over a fetched cover, ignored when the track moved on while it was downloading
+    **/
+    pub fn set_image(&mut self, url : &str, image : DynamicImage)
+    {
+        if self.url.as_deref() == Some(url) {
+            self.image = Some(image);
+            self.prepared = None;
+        }
+    }
+
+    pub fn cache_dir(&self) -> PathBuf
+    {
+        self.cache_dir.clone()
+    }
+
+    /**
+        Downloads and decodes a cover, runs off in a background task so it only
+        gets the cache dir, spotify cover urls end in a unique id so that is the cache key
+    **/
+    pub async fn fetch(cache_dir : PathBuf, url : &str) -> Result<DynamicImage, Box<dyn Error>>
+    {
+        let key = paths::sanitize_file_name(url.rsplit('/').next().unwrap_or(url));
+        let cache_path = cache_dir.join(key);
+
+        let bytes = match fs::read(&cache_path) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                let bytes = Client::new()
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await?
+                    .to_vec();
+
+                fs::create_dir_all(&cache_dir)?;
+                fs::write(&cache_path, &bytes)?;
+                bytes
+            }
+        };
+        Ok(image::load_from_memory(&bytes)?)
+    }
+
+    /**
+        Gets the cover ready for a box of cols x rows cells, the scaled pixels
+        or escape sequence is kept around until the size or cover changes
+    **/
+    pub fn prepare(&mut self, cols : u16, rows : u16) -> Result<(), Box<dyn Error>>
+    {
+        if self.prepared.as_ref().is_some_and(|p| p.cols == cols && p.rows == rows) {
+            return Ok(());
+        }
+
+        let Some(image) = &self.image else {
+            self.prepared = None;
+            return Ok(());
+        };
+
+        let content = match self.protocol
+        {
+            ArtProtocol::HalfBlock => PreparedContent::Pixels(image.resize_exact(cols as u32, rows as u32 * 2, FilterType::Triangle).to_rgb8()),
+            ArtProtocol::Kitty => match AlbumArt::encode_kitty(image, cols, rows) {
+                Ok(escape) => PreparedContent::Escape(escape),
+                Err(e) => {
+                    // the same cover would only fail again on every frame
+                    self.image = None;
+                    return Err(e);
+                }
+            },
+            ArtProtocol::Sixel => PreparedContent::Escape(AlbumArt::encode_sixel(image, cols, rows)),
+            ArtProtocol::Off => return Ok(()),
+        };
+
+        self.prepared = Some(PreparedArt { cols, rows, content });
+        Ok(())
+    }
+
+    /**
+        Draws the prepared cover into the frame with its top left at x, y
+    **/
+    pub fn draw(&self, frame : &mut Frame, x : u16, y : u16)
+    {
+        let Some(prepared) = &self.prepared else {
+            return;
+        };
+
+        match &prepared.content
+        {
+            // two pixels per cell, the top one is the ▀ and the bottom one the background
+            PreparedContent::Pixels(pixels) => {
+                for row in 0..prepared.rows
+                {
+                    for col in 0..prepared.cols
+                    {
+                        let top = pixels.get_pixel(col as u32, row as u32 * 2);
+                        let bottom = pixels.get_pixel(col as u32, row as u32 * 2 + 1);
+                        frame.set_cell(x + col, y + row, "▀", Style {
+                            fg : Some(Color::Rgb { r : top[0], g : top[1], b : top[2] }),
+                            bg : Some(Color::Rgb { r : bottom[0], g : bottom[1], b : bottom[2] }),
+                            ..Style::default()
+                        });
+                    }
+                }
+            }
+            PreparedContent::Escape(payload) => {
+                frame.graphics.push(Graphic {
+                    x,
+                    y,
+                    payload : payload.clone(),
+                    clear : if self.protocol == ArtProtocol::Kitty { "\x1b_Ga=d,q=2\x1b\\" } else { "" },
+                });
+            }
+        }
+    }
+
+    /**
+        Kitty scales the png to fit the cell box itself so we just send it,
+        the payload has to go out in chunks of at most 4096 bytes
+    **/
+    fn encode_kitty(image : &DynamicImage, cols : u16, rows : u16) -> Result<String, Box<dyn Error>>
+    {
+        let mut png : Vec<u8> = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        let encoded = STANDARD.encode(&png);
+
+        let mut escape = String::new();
+        let chunks : Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+        for (i, chunk) in chunks.iter().enumerate()
+        {
+            let more = if i + 1 < chunks.len() { 1 } else { 0 };
+            let control = if i == 0 {
+                format!("f=100,a=T,c={},r={},C=1,q=2,m={}", cols, rows, more)
+            } else {
+                format!("m={}", more)
+            };
+            escape.push_str(&format!("\x1b_G{};{}\x1b\\", control, std::str::from_utf8(chunk)?));
+        }
+        Ok(escape)
+    }
+
+    /**
+        Sixel needs real pixels so the cell size comes from the terminal,
+        colours are squashed into a 6x6x6 cube so the palette stays small
+    **/
+    fn encode_sixel(image : &DynamicImage, cols : u16, rows : u16) -> String
+    {
+        let (cell_width, cell_height) = match terminal::window_size() {
+            Ok(size) if size.width > 0 && size.columns > 0 && size.rows > 0 => {
+                ((size.width / size.columns) as u32, (size.height / size.rows) as u32)
+            }
+            _ => (8, 16),
+        };
+
+        let target_width = cols as u32 * cell_width;
+        // sixels are drawn six pixel rows at a time so round down to a band
+        let target_height = (rows as u32 * cell_height) / 6 * 6;
+        let pixels = image.resize_exact(target_width, target_height, FilterType::Triangle).to_rgb8();
+        let (width, height) = pixels.dimensions();
+
+        let level = |c : u8| (c as u32 * 5 + 127) / 255;
+        let index_of = |p : &image::Rgb<u8>| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize;
+
+        let mut sixel = String::from("\x1bPq");
+        sixel.push_str(&format!("\"1;1;{};{}", width, height));
+        for i in 0..216
+        {
+            let (r, g, b) = (i / 36, (i / 6) % 6, i % 6);
+            sixel.push_str(&format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20));
+        }
+
+        for band in (0..height).step_by(6)
+        {
+            let band_rows = (height - band).min(6);
+
+            // which palette entries show up in this band and where
+            let mut bits : Vec<Vec<u8>> = vec![Vec::new(); 216];
+            for col in 0..width
+            {
+                for dy in 0..band_rows
+                {
+                    let index = index_of(pixels.get_pixel(col, band + dy));
+                    if bits[index].is_empty() {
+                        bits[index] = vec![0; width as usize];
+                    }
+                    bits[index][col as usize] |= 1 << dy;
+                }
+            }
+
+            for (index, columns) in bits.iter().enumerate().filter(|(_, c)| !c.is_empty())
+            {
+                sixel.push_str(&format!("#{}", index));
+                // run length encode repeated columns with !count
+                let mut col = 0;
+                while col < columns.len()
+                {
+                    let value = columns[col];
+                    let run = columns[col..].iter().take_while(|v| **v == value).count();
+                    let ch = (0x3f + value) as char;
+                    if run > 3 {
+                        sixel.push_str(&format!("!{}{}", run, ch));
+                    } else {
+                        sixel.extend(std::iter::repeat_n(ch, run));
+                    }
+                    col += run;
+                }
+                sixel.push('$');
+            }
+            sixel.push('-');
+        }
+        sixel.push_str("\x1b\\");
+        sixel
+    }
+}
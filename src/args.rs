@@ -0,0 +1,57 @@
+use super::layout::{self, LayoutPreset};
+use super::logging::{self, Level};
+
+/*
+
+The command line flags, there are only a few so they get read by hand,
+anything given here wins over the config
+
+*/
+
+#[derive(Default)]
+pub struct Args
+{
+    pub layout : Option<LayoutPreset>,
+    pub log_level : Option<Level>,
+}
+
+impl Args
+{
+    /**
+        Reads the flags after the program name, a value can follow the
+        flag or go after an = like --layout=compact
+    **/
+    pub fn parse(args : impl Iterator<Item = String>) -> Result<Self, String>
+    {
+        let mut parsed = Args::default();
+        let mut args = args;
+
+        while let Some(arg) = args.next()
+        {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match flag.as_str()
+            {
+                "--layout" => {
+                    let names = layout::layout_preset_names().collect::<Vec<_>>().join(", ");
+                    let value = value.or_else(|| args.next()).ok_or_else(|| format!("--layout needs one of {}", names))?;
+                    let preset = LayoutPreset::from_name(&value)
+                        .ok_or_else(|| format!("unknown layout \"{}\", the layouts are {}", value, names))?;
+                    parsed.layout = Some(preset);
+                }
+                "--log-level" => {
+                    let names = logging::level_names().collect::<Vec<_>>().join(", ");
+                    let value = value.or_else(|| args.next()).ok_or_else(|| format!("--log-level needs one of {}", names))?;
+                    let level = Level::from_name(&value)
+                        .ok_or_else(|| format!("unknown log level \"{}\", the levels are {}", value, names))?;
+                    parsed.log_level = Some(level);
+                }
+                _ => return Err(format!("unknown flag {}", flag)),
+            }
+        }
+        Ok(parsed)
+    }
+}
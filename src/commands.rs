@@ -0,0 +1,468 @@
+use super::mode::Mode;
+
+/*
+
+Every : command in one table, each one says what it is called, the
+shortest abbreviation it answers to, what arguments it takes and a line of
+help, the tui only has to look up what was typed here and run it
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CommandId
+{
+    Quit,
+    Clear,
+    PrintToken,
+    Pause,
+    Next,
+    Previous,
+    Volume,
+    Device,
+    Playlist,
+    Map,
+    Unmap,
+    Source,
+    Theme,
+    Help
+}
+
+/**
+        What an argument holds, used for completion and for telling an
+        optional argument apart from the one after it
+**/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArgKind
+{
+    Mode,
+    Keys,
+    Action,
+    Command,
+    Device,
+    Playlist,
+    Volume,
+    File,
+    Theme
+}
+
+impl ArgKind
+{
+    /**
+        Whether a value could be meant for this kind of argument, only
+        modes are picky since anything can be a key or a name
+    **/
+    pub fn accepts(&self, value : &str) -> bool
+    {
+        match self
+        {
+            ArgKind::Mode => Mode::from_keymap_name(value).is_some(),
+            _ => true,
+        }
+    }
+}
+
+pub struct ArgSpec
+{
+    pub name : &'static str,
+    pub kind : ArgKind,
+    pub optional : bool,
+    // takes everything that is left joined back up with spaces, for names with spaces in them
+    pub rest : bool,
+}
+
+const fn arg(name : &'static str, kind : ArgKind) -> ArgSpec
+{
+    ArgSpec { name, kind, optional : false, rest : false }
+}
+
+const fn optional(name : &'static str, kind : ArgKind) -> ArgSpec
+{
+    ArgSpec { name, kind, optional : true, rest : false }
+}
+
+const fn rest(name : &'static str, kind : ArgKind) -> ArgSpec
+{
+    ArgSpec { name, kind, optional : false, rest : true }
+}
+
+pub struct CommandSpec
+{
+    pub id : CommandId,
+    pub name : &'static str,
+    // how much of the name has to be typed, like vim's q[uit]
+    pub min_len : usize,
+    pub aliases : &'static [&'static str],
+    pub args : &'static [ArgSpec],
+    pub help : &'static str,
+}
+
+pub const COMMANDS : &[CommandSpec] = &[
+    CommandSpec { id : CommandId::Quit, name : "quit", min_len : 1, aliases : &["exit"], args : &[], help : "Quit comfyfy" },
+    CommandSpec { id : CommandId::Clear, name : "clear", min_len : 1, aliases : &[], args : &[], help : "Clear the notifications and redraw the screen" },
+    CommandSpec { id : CommandId::PrintToken, name : "print_token", min_len : 2, aliases : &[], args : &[], help : "Show the spotify access token" },
+    CommandSpec { id : CommandId::Pause, name : "pause", min_len : 2, aliases : &["play"], args : &[], help : "Pause or resume playback" },
+    CommandSpec { id : CommandId::Next, name : "next", min_len : 1, aliases : &[], args : &[], help : "Skip to the next track" },
+    CommandSpec { id : CommandId::Previous, name : "previous", min_len : 4, aliases : &[], args : &[], help : "Go back to the previous track" },
+    CommandSpec {
+        id : CommandId::Volume,
+        name : "volume",
+        min_len : 3,
+        aliases : &[],
+        args : &[arg("percent", ArgKind::Volume)],
+        help : "Set the volume, +n and -n turn it up or down",
+    },
+    CommandSpec {
+        id : CommandId::Device,
+        name : "device",
+        min_len : 3,
+        aliases : &[],
+        args : &[rest("name", ArgKind::Device)],
+        help : "Move playback to another device",
+    },
+    CommandSpec {
+        id : CommandId::Playlist,
+        name : "playlist",
+        min_len : 2,
+        aliases : &[],
+        args : &[rest("name", ArgKind::Playlist)],
+        help : "Play one of your playlists",
+    },
+    CommandSpec {
+        id : CommandId::Map,
+        name : "map",
+        min_len : 3,
+        aliases : &[],
+        args : &[optional("mode", ArgKind::Mode), arg("keys", ArgKind::Keys), arg("action", ArgKind::Action)],
+        help : "Bind keys to an action, in normal mode unless a mode is given",
+    },
+    CommandSpec {
+        id : CommandId::Unmap,
+        name : "unmap",
+        min_len : 3,
+        aliases : &[],
+        args : &[optional("mode", ArgKind::Mode), arg("keys", ArgKind::Keys)],
+        help : "Remove a key binding",
+    },
+    CommandSpec {
+        id : CommandId::Source,
+        name : "source",
+        min_len : 2,
+        aliases : &[],
+        args : &[rest("file", ArgKind::File)],
+        help : "Run the commands in a file, one per line",
+    },
+    CommandSpec {
+        id : CommandId::Theme,
+        name : "theme",
+        min_len : 2,
+        aliases : &["colorscheme"],
+        args : &[arg("preset", ArgKind::Theme)],
+        help : "Switch to the dark, light or high-contrast theme",
+    },
+    CommandSpec {
+        id : CommandId::Help,
+        name : "help",
+        min_len : 1,
+        aliases : &[],
+        args : &[optional("command", ArgKind::Command)],
+        help : "List the commands, or show how to use one",
+    },
+];
+
+impl CommandSpec
+{
+    /**
+        True when what was typed is this command, its abbreviation or an alias
+    **/
+    pub fn matches(&self, typed : &str) -> bool
+    {
+        (typed.len() >= self.min_len && self.name.starts_with(typed)) || self.aliases.contains(&typed)
+    }
+
+    /**
+        The name written the vim way with the optional part in brackets, like q[uit]
+    **/
+    pub fn display_name(&self) -> String
+    {
+        if self.min_len >= self.name.len() {
+            self.name.to_string()
+        } else {
+            format!("{}[{}]", &self.name[..self.min_len], &self.name[self.min_len..])
+        }
+    }
+
+    pub fn usage(&self) -> String
+    {
+        let mut usage = format!(":{}", self.display_name());
+        for arg in self.args
+        {
+            if arg.optional {
+                usage.push_str(&format!(" [{}]", arg.name));
+            } else {
+                usage.push_str(&format!(" <{}>", arg.name));
+            }
+        }
+        usage
+    }
+
+    /**
+        The argument the next word typed goes into, given the words already
+        typed, a leading optional argument is skipped when its word doesnt fit
+    **/
+    pub fn arg_at(&self, previous : &[&str]) -> Option<&ArgSpec>
+    {
+        let skipped = match (self.args.first(), previous.first()) {
+            (Some(first), Some(typed)) if first.optional && !first.kind.accepts(typed) => 1,
+            _ => 0,
+        };
+
+        match self.args.get(previous.len() + skipped) {
+            Some(arg) => Some(arg),
+            None => self.args.last().filter(|a| a.rest),
+        }
+    }
+
+    /**
+        Lines up the words with the arguments, missing optional ones come
+        back as None so the command can tell them apart
+    **/
+    fn bind_args(&self, mut words : Vec<String>) -> Result<Vec<Option<String>>, String>
+    {
+        let required = self.args.iter().filter(|a| !a.optional).count();
+        let takes_rest = self.args.last().is_some_and(|a| a.rest);
+
+        if words.len() < required || (!takes_rest && words.len() > self.args.len()) {
+            return Err(format!("usage {}", self.usage()));
+        }
+
+        // everything past the rest argument belongs to it
+        if takes_rest && words.len() > self.args.len() {
+            let joined = words.split_off(self.args.len() - 1).join(" ");
+            words.push(joined);
+        }
+
+        // leave out optional arguments from the front until the rest line up
+        let mut missing = self.args.len() - words.len();
+        let mut bound : Vec<Option<String>> = Vec::new();
+        let mut words = words.into_iter().peekable();
+        for arg in self.args
+        {
+            let fits = words.peek().is_some_and(|w| arg.kind.accepts(w));
+            if arg.optional && missing > 0 && !fits {
+                bound.push(None);
+                missing -= 1;
+            } else {
+                bound.push(words.next());
+            }
+        }
+
+        // an optional word took the place of one that has to be there
+        if self.args.iter().zip(&bound).any(|(arg, value)| !arg.optional && value.is_none()) {
+            return Err(format!("usage {}", self.usage()));
+        }
+        Ok(bound)
+    }
+}
+
+/**
+        Finds the command for what was typed, an exact name wins over an
+        abbreviation and an abbreviation that fits several is an error
+**/
+pub fn lookup(typed : &str) -> Result<&'static CommandSpec, String>
+{
+    if let Some(exact) = COMMANDS.iter().find(|c| c.name == typed) {
+        return Ok(exact);
+    }
+
+    let found : Vec<&CommandSpec> = COMMANDS.iter().filter(|c| c.matches(typed)).collect();
+    match found.as_slice() {
+        [command] => Ok(command),
+        [] => Err(format!("Not a command: {}", typed)),
+        several => Err(format!(
+            "Ambiguous command {}: {}",
+            typed,
+            several.iter().map(|c| c.name).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/**
+        True while what was typed could still turn into a command
+**/
+pub fn could_be_command(typed : &str) -> bool
+{
+    COMMANDS.iter().any(|c| c.name.starts_with(typed) || c.aliases.iter().any(|a| a.starts_with(typed)))
+}
+
+/**
+        Splits a command line into words, quotes keep spaces together and a
+        backslash takes the next character as is
+**/
+pub fn tokenize(line : &str) -> Result<Vec<String>, String>
+{
+    let mut words : Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote : Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next()
+    {
+        match (c, quote)
+        {
+            ('\\', _) => {
+                word.push(chars.next().ok_or("nothing after \\")?);
+                in_word = true;
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => word.push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (c, None) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (c, None) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("missing closing {}", q));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+pub struct ParsedCommand
+{
+    pub spec : &'static CommandSpec,
+    // one per argument in the spec, None for optional ones left out
+    pub args : Vec<Option<String>>,
+}
+
+impl ParsedCommand
+{
+    /**
+        The argument by name, only None for optional arguments that were left out
+    **/
+    pub fn get(&self, name : &str) -> Option<&str>
+    {
+        let index = self.spec.args.iter().position(|a| a.name == name)?;
+        self.args.get(index)?.as_deref()
+    }
+}
+
+/**
+        Reads a whole command line like "map normal gg select_first", a
+        leading : is allowed so lines from files can be written either way
+**/
+pub fn parse(line : &str) -> Result<Option<ParsedCommand>, String>
+{
+    let line = line.trim();
+    let line = line.strip_prefix(':').unwrap_or(line);
+
+    let mut words = tokenize(line)?;
+    if words.is_empty() {
+        return Ok(None);
+    }
+
+    let spec = lookup(&words.remove(0))?;
+    let args = spec.bind_args(words)?;
+    Ok(Some(ParsedCommand { spec, args }))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn spec(id : CommandId) -> &'static CommandSpec
+    {
+        COMMANDS.iter().find(|c| c.id == id).unwrap()
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_spaces()
+    {
+        assert_eq!(tokenize(r#"device "Living Room" 'x y'"#).unwrap(), vec!["device", "Living Room", "x y"]);
+    }
+
+    #[test]
+    fn tokenize_rejects_an_unterminated_quote()
+    {
+        assert_eq!(tokenize(r#"device "Living Room"#), Err("missing closing \"".to_string()));
+        assert!(tokenize("map 'gg").is_err());
+    }
+
+    #[test]
+    fn tokenize_escapes_a_quote_inside_quotes()
+    {
+        assert_eq!(tokenize(r#"playlist "say \"hi\"""#).unwrap(), vec!["playlist", r#"say "hi""#]);
+    }
+
+    #[test]
+    fn tokenize_rejects_a_trailing_backslash()
+    {
+        assert!(tokenize("map \\").is_err());
+    }
+
+    #[test]
+    fn lookup_resolves_abbreviations()
+    {
+        assert_eq!(lookup("pa").unwrap().id, CommandId::Pause);
+        assert_eq!(lookup("pl").unwrap().id, CommandId::Playlist);
+        assert_eq!(lookup("prev").unwrap().id, CommandId::Previous);
+        assert_eq!(lookup("exit").unwrap().id, CommandId::Quit);
+    }
+
+    #[test]
+    fn lookup_rejects_a_prefix_too_short_for_every_command()
+    {
+        // pause, playlist, print_token and previous all start with p
+        assert!(lookup("p").is_err());
+        assert!(lookup("pre").is_err());
+    }
+
+    #[test]
+    fn bind_args_with_a_mode()
+    {
+        let words = vec!["visual".to_string(), "q".to_string(), "cancel".to_string()];
+        assert_eq!(
+            spec(CommandId::Map).bind_args(words).unwrap(),
+            vec![Some("visual".to_string()), Some("q".to_string()), Some("cancel".to_string())]
+        );
+    }
+
+    #[test]
+    fn bind_args_without_a_mode()
+    {
+        let words = vec!["gg".to_string(), "select_first".to_string()];
+        assert_eq!(
+            spec(CommandId::Map).bind_args(words).unwrap(),
+            vec![None, Some("gg".to_string()), Some("select_first".to_string())]
+        );
+    }
+
+    #[test]
+    fn bind_args_joins_the_rest()
+    {
+        let words = vec!["Living".to_string(), "Room".to_string()];
+        assert_eq!(spec(CommandId::Device).bind_args(words).unwrap(), vec![Some("Living Room".to_string())]);
+    }
+
+    #[test]
+    fn bind_args_rejects_missing_and_extra_words()
+    {
+        assert!(spec(CommandId::Map).bind_args(vec!["gg".to_string()]).is_err());
+        assert!(spec(CommandId::Volume).bind_args(vec!["1".to_string(), "2".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf
+};
+use serde::Deserialize;
+
+use super::paths;
+use super::keymap::Keymap;
+use super::layout::{LayoutConfig, LayoutNode, LayoutPreset};
+use super::status_bar::{StatusBar, StatusBarConfig};
+use super::theme::{ColorDepth, Preset, Theme, ThemeConfig};
+
+/*
+
+User settings from ~/.config/comfyfy/config.toml, everything in there is
+optional and a missing file just means the defaults
+
+*/
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile
+{
+    layout : Option<LayoutConfig>,
+    display : Option<DisplayConfig>,
+    controls : Option<ControlsConfig>,
+    theme : Option<ThemeConfig>,
+    status : Option<StatusBarConfig>,
+    // a table per mode of keys to action names
+    keys : Option<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleOverflow
+{
+    // cut off with "..." on the end
+    #[default]
+    Truncate,
+    // scrolls along so the whole title gets seen
+    Marquee
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlMode
+{
+    // p, b and f pause and skip straight away
+    #[default]
+    Direct,
+    // p, b and f only move the focus and enter does the rest
+    Focus
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ControlsConfig
+{
+    mode : Option<ControlMode>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DisplayConfig
+{
+    title_overflow : Option<TitleOverflow>,
+}
+
+pub struct Config
+{
+    // the full layout, the preset decides when it gets used
+    pub layout : LayoutNode,
+    pub layout_preset : LayoutPreset,
+    pub title_overflow : TitleOverflow,
+    pub control_mode : ControlMode,
+    pub keymap : Keymap,
+    pub theme : Theme,
+    // kept around so :theme can put its styles on top of another preset
+    pub theme_config : ThemeConfig,
+    pub color_depth : ColorDepth,
+    pub status_bar : StatusBar,
+}
+
+impl Default for Config
+{
+    fn default() -> Self
+    {
+        Config {
+            layout : LayoutNode::default_layout(),
+            layout_preset : LayoutPreset::default(),
+            title_overflow : TitleOverflow::default(),
+            control_mode : ControlMode::default(),
+            keymap : Keymap::default(),
+            theme : Theme::for_depth(Preset::default(), ColorDepth::detect()),
+            theme_config : ThemeConfig::default(),
+            color_depth : ColorDepth::detect(),
+            status_bar : StatusBar::default(),
+        }
+    }
+}
+
+impl Config
+{
+    /**
+        COMFYFY_CONFIG can point somewhere else, handy for trying out a layout
+    **/
+    pub fn path() -> PathBuf
+    {
+        match std::env::var_os("COMFYFY_CONFIG") {
+            Some(path) => PathBuf::from(path),
+            None => paths::config_dir().join("config.toml"),
+        }
+    }
+
+    /**
+        The commands run at startup, COMFYFY_RC can point somewhere else
+    **/
+    pub fn rc_path() -> PathBuf
+    {
+        match std::env::var_os("COMFYFY_RC") {
+            Some(path) => PathBuf::from(path),
+            None => paths::config_dir().join("comfyfyrc"),
+        }
+    }
+
+    /**
+        Reads and checks the config, errors name the file so they can be shown at startup
+    **/
+    pub fn load() -> Result<Self, String>
+    {
+        let path = Config::path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let file : ConfigFile = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut config = Config::default();
+        if let Some(layout) = file.layout {
+            config.layout = layout.to_node().map_err(|e| format!("{}: [layout] {}", path.display(), e))?;
+        }
+        if let Some(title_overflow) = file.display.and_then(|d| d.title_overflow) {
+            config.title_overflow = title_overflow;
+        }
+        if let Some(control_mode) = file.controls.and_then(|c| c.mode) {
+            config.control_mode = control_mode;
+        }
+        if let Some(theme) = file.theme {
+            (config.theme, config.color_depth) = theme.to_theme().map_err(|e| format!("{}: [theme] {}", path.display(), e))?;
+            config.theme_config = theme;
+        }
+        if let Some(status) = file.status {
+            config.status_bar = status.to_status_bar();
+        }
+        if let Some(keys) = file.keys {
+            config.keymap.apply_config(&keys).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(config)
+    }
+}
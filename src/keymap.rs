@@ -0,0 +1,599 @@
+use std::collections::{BTreeMap, HashMap};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::mode::{Mode, KEYMAP_MODES};
+
+/*
+
+Which keys do what, every mode has its own list of key sequences bound to
+named actions, the defaults can be changed from the [keys] section of the
+config or at runtime with :map and :unmap
+
+Keys are written the vim way, plain characters stand for themselves and
+special keys or chords go in angle brackets like <Space>, <CR> or <C-c>
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action
+{
+    Quit,
+    // back to normal mode from wherever
+    Cancel,
+    CommandLine,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Visual,
+    // puts the rows picked in visual mode on the queue
+    QueueSelection,
+    Help,
+    Suspend,
+    SelectPause,
+    SelectSkipBack,
+    SelectSkipForward,
+    // moves the focus along the controls
+    FocusNext,
+    FocusPrevious,
+    // runs whichever control is selected
+    Activate,
+    OpenAlbum,
+    OpenArtist,
+    PlayArtist,
+    SavedShows,
+    ToggleLyrics,
+    SeekBack,
+    SeekForward,
+    SeekBackLong,
+    SeekForwardLong,
+    VolumeUp,
+    VolumeDown,
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    OpenSelected,
+    Back
+}
+
+// the names actions go by in the config and in :map
+const ACTIONS : &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("cancel", Action::Cancel),
+    ("command_line", Action::CommandLine),
+    ("search", Action::Search),
+    ("search_next", Action::SearchNext),
+    ("search_previous", Action::SearchPrevious),
+    ("visual", Action::Visual),
+    ("queue_selection", Action::QueueSelection),
+    ("help", Action::Help),
+    ("suspend", Action::Suspend),
+    ("select_pause", Action::SelectPause),
+    ("select_skip_back", Action::SelectSkipBack),
+    ("select_skip_forward", Action::SelectSkipForward),
+    ("focus_next", Action::FocusNext),
+    ("focus_previous", Action::FocusPrevious),
+    ("activate", Action::Activate),
+    ("open_album", Action::OpenAlbum),
+    ("open_artist", Action::OpenArtist),
+    ("play_artist", Action::PlayArtist),
+    ("saved_shows", Action::SavedShows),
+    ("toggle_lyrics", Action::ToggleLyrics),
+    ("seek_back", Action::SeekBack),
+    ("seek_forward", Action::SeekForward),
+    ("seek_back_long", Action::SeekBackLong),
+    ("seek_forward_long", Action::SeekForwardLong),
+    ("volume_up", Action::VolumeUp),
+    ("volume_down", Action::VolumeDown),
+    ("select_next", Action::SelectNext),
+    ("select_previous", Action::SelectPrevious),
+    ("select_first", Action::SelectFirst),
+    ("select_last", Action::SelectLast),
+    ("open_selected", Action::OpenSelected),
+    ("back", Action::Back),
+];
+
+/**
+        The modes keys can be bound in, for error messages
+**/
+pub fn keymap_mode_names() -> String
+{
+    KEYMAP_MODES.iter().map(|m| m.name()).collect::<Vec<_>>().join(", ")
+}
+
+/**
+        Every action name, for completing :map
+**/
+pub fn action_names() -> impl Iterator<Item = &'static str>
+{
+    ACTIONS.iter().map(|(name, _)| *name)
+}
+
+impl Action
+{
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, action)| *action)
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        ACTIONS.iter().find(|(_, action)| action == self).map(|(n, _)| *n).unwrap_or("unknown")
+    }
+
+    /**
+        What the action does, for the help overlay
+    **/
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            Action::Quit => "Quit comfyfy",
+            Action::Cancel => "Back to normal mode",
+            Action::CommandLine => "Type a command",
+            Action::Search => "Search the open list",
+            Action::SearchNext => "Next match",
+            Action::SearchPrevious => "Previous match",
+            Action::Visual => "Select a range of rows",
+            Action::QueueSelection => "Queue the selected rows",
+            Action::Help => "Show this help",
+            Action::Suspend => "Suspend to the shell",
+            Action::SelectPause => "Pause or play",
+            Action::SelectSkipBack => "Skip back",
+            Action::SelectSkipForward => "Skip forward",
+            Action::FocusNext => "Focus the next control",
+            Action::FocusPrevious => "Focus the previous control",
+            Action::Activate => "Run the focused control",
+            Action::OpenAlbum => "Open the playing album or show",
+            Action::OpenArtist => "Open the playing artist",
+            Action::PlayArtist => "Play songs by the playing artist",
+            Action::SavedShows => "Browse your saved shows",
+            Action::ToggleLyrics => "Show or hide the lyrics",
+            Action::SeekBack => "Back 15 seconds",
+            Action::SeekForward => "Forward 15 seconds",
+            Action::SeekBackLong => "Back 30 seconds",
+            Action::SeekForwardLong => "Forward 30 seconds",
+            Action::VolumeUp => "Volume up",
+            Action::VolumeDown => "Volume down",
+            Action::SelectNext => "Move down",
+            Action::SelectPrevious => "Move up",
+            Action::SelectFirst => "Go to the top",
+            Action::SelectLast => "Go to the bottom",
+            Action::OpenSelected => "Open the selected row",
+            Action::Back => "Close the view",
+        }
+    }
+}
+
+// names for the keys that dont print as a single character, matched
+// ignoring case and the first name for a key is the one shown
+const KEY_NAMES : &[(&str, KeyCode)] = &[
+    ("Space", KeyCode::Char(' ')),
+    ("CR", KeyCode::Enter),
+    ("Enter", KeyCode::Enter),
+    ("Return", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("BS", KeyCode::Backspace),
+    ("Backspace", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Del", KeyCode::Delete),
+    ("Delete", KeyCode::Delete),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("lt", KeyCode::Char('<')),
+];
+
+/**
+        One key press along with the modifiers held down for it
+**/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyChord
+{
+    pub code : KeyCode,
+    pub modifiers : KeyModifiers,
+}
+
+impl KeyChord
+{
+    pub fn new(code : KeyCode, modifiers : KeyModifiers) -> Self
+    {
+        // shift is already in the character itself, G is just G
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyChord { code, modifiers }
+    }
+
+    pub fn from_event(event : &KeyEvent) -> Self
+    {
+        KeyChord::new(event.code, event.modifiers)
+    }
+
+    /**
+        Reads what is inside the angle brackets, like "C-c", "Space" or "A-Left"
+    **/
+    fn parse_bracketed(name : &str) -> Result<Self, String>
+    {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = name;
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-'
+        {
+            modifiers |= match rest.as_bytes()[0].to_ascii_lowercase() {
+                b'c' => KeyModifiers::CONTROL,
+                b'a' | b'm' => KeyModifiers::ALT,
+                b's' => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier in <{}>", name)),
+            };
+            rest = &rest[2..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(rest)) {
+                Some((_, code)) => *code,
+                None => return Err(format!("unknown key <{}>", name)),
+            },
+        };
+        Ok(KeyChord::new(code, modifiers))
+    }
+
+    /**
+        Writes the chord back out in the same notation the config uses
+    **/
+    pub fn display(&self) -> String
+    {
+        let name = match self.code {
+            KeyCode::Char(c) if c != ' ' && c != '<' => c.to_string(),
+            code => KEY_NAMES.iter()
+                .find(|(_, c)| *c == code)
+                .map(|(n, _)| n.to_string())
+                .unwrap_or_else(|| format!("{:?}", code)),
+        };
+
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            prefix.push_str("S-");
+        }
+
+        if prefix.is_empty() && name.chars().count() == 1 {
+            name
+        } else {
+            format!("<{}{}>", prefix, name)
+        }
+    }
+}
+
+/**
+        Splits a string like "gg", "<C-c>" or "<Space>p" into the chords it
+        is made of, a < without a closing > is just the < key
+**/
+pub fn parse_keys(keys : &str) -> Result<Vec<KeyChord>, String>
+{
+    let mut chords : Vec<KeyChord> = Vec::new();
+    let mut rest = keys;
+
+    while let Some(c) = rest.chars().next()
+    {
+        if c == '<' {
+            if let Some(end) = rest[1..].find('>').map(|e| e + 1).filter(|e| *e > 1) {
+                chords.push(KeyChord::parse_bracketed(&rest[1..end])?);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        chords.push(KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if chords.is_empty() {
+        return Err("no keys given".to_string());
+    }
+    Ok(chords)
+}
+
+pub fn format_keys(keys : &[KeyChord]) -> String
+{
+    keys.iter().map(|k| k.display()).collect()
+}
+
+pub enum KeyLookup
+{
+    Action(Action),
+    // the keys so far are the start of a longer binding
+    Pending,
+    None
+}
+
+pub struct Keymap
+{
+    bindings : HashMap<Mode, Vec<(Vec<KeyChord>, Action)>>,
+}
+
+impl Default for Keymap
+{
+    fn default() -> Self
+    {
+        let normal : &[(&str, Action)] = &[
+            ("<C-c>", Action::Quit),
+            ("<C-z>", Action::Suspend),
+            (":", Action::CommandLine),
+            ("/", Action::Search),
+            ("n", Action::SearchNext),
+            ("N", Action::SearchPrevious),
+            ("v", Action::Visual),
+            ("?", Action::Help),
+            ("p", Action::SelectPause),
+            ("<Space>", Action::SelectPause),
+            ("b", Action::SelectSkipBack),
+            ("<Left>", Action::SelectSkipBack),
+            ("f", Action::SelectSkipForward),
+            ("<Right>", Action::SelectSkipForward),
+            ("<Tab>", Action::FocusNext),
+            ("l", Action::FocusNext),
+            ("<BackTab>", Action::FocusPrevious),
+            ("h", Action::FocusPrevious),
+            ("<CR>", Action::Activate),
+            ("a", Action::OpenAlbum),
+            ("r", Action::OpenArtist),
+            ("R", Action::PlayArtist),
+            ("s", Action::SavedShows),
+            ("y", Action::ToggleLyrics),
+            (",", Action::SeekBack),
+            (".", Action::SeekForward),
+            ("<", Action::SeekBackLong),
+            (">", Action::SeekForwardLong),
+            ("+", Action::VolumeUp),
+            ("-", Action::VolumeDown),
+            ("j", Action::SelectNext),
+            ("<Down>", Action::SelectNext),
+            ("k", Action::SelectPrevious),
+            ("<Up>", Action::SelectPrevious),
+            ("gg", Action::SelectFirst),
+            ("G", Action::SelectLast),
+            ("o", Action::OpenSelected),
+            ("<BS>", Action::Back),
+            ("<Esc>", Action::Back),
+        ];
+        let visual : &[(&str, Action)] = &[
+            ("<C-c>", Action::Cancel),
+            ("<Esc>", Action::Cancel),
+            ("v", Action::Cancel),
+            ("<C-z>", Action::Suspend),
+            ("j", Action::SelectNext),
+            ("<Down>", Action::SelectNext),
+            ("k", Action::SelectPrevious),
+            ("<Up>", Action::SelectPrevious),
+            ("gg", Action::SelectFirst),
+            ("G", Action::SelectLast),
+            ("q", Action::QueueSelection),
+            ("<CR>", Action::QueueSelection),
+        ];
+        let help : &[(&str, Action)] = &[
+            ("<C-c>", Action::Cancel),
+            ("<Esc>", Action::Cancel),
+            ("q", Action::Cancel),
+            ("?", Action::Cancel),
+            ("<C-z>", Action::Suspend),
+            ("j", Action::SelectNext),
+            ("<Down>", Action::SelectNext),
+            ("k", Action::SelectPrevious),
+            ("<Up>", Action::SelectPrevious),
+            ("gg", Action::SelectFirst),
+            ("G", Action::SelectLast),
+        ];
+        let popup : &[(&str, Action)] = &[
+            ("<C-c>", Action::Cancel),
+            ("<Esc>", Action::Cancel),
+            ("q", Action::Cancel),
+            ("<CR>", Action::Cancel),
+            ("<C-z>", Action::Suspend),
+        ];
+
+        let mut keymap = Keymap { bindings : HashMap::new() };
+        for (mode, bindings) in [(Mode::Normal, normal), (Mode::Visual, visual), (Mode::Help, help), (Mode::Popup, popup)]
+        {
+            for (keys, action) in bindings
+            {
+                let keys = parse_keys(keys).expect("default keys parse");
+                keymap.bind(mode, keys, *action).expect("default keys dont conflict");
+            }
+        }
+        keymap
+    }
+}
+
+impl Keymap
+{
+    /**
+        Binds keys to an action, replacing whatever they did before, keys
+        that start or are the start of another binding are refused since
+        the shorter one could never be told apart from the longer one
+    **/
+    pub fn bind(&mut self, mode : Mode, keys : Vec<KeyChord>, action : Action) -> Result<(), String>
+    {
+        let bindings = self.bindings.entry(mode).or_default();
+
+        let conflict = bindings.iter().find(|(bound, _)| {
+            *bound != keys && (bound.starts_with(&keys) || keys.starts_with(bound))
+        });
+        if let Some((bound, bound_action)) = conflict {
+            return Err(format!(
+                "{} conflicts with {} ({}), unmap it first",
+                format_keys(&keys),
+                format_keys(bound),
+                bound_action.name()
+            ));
+        }
+
+        bindings.retain(|(bound, _)| *bound != keys);
+        bindings.push((keys, action));
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, mode : Mode, keys : &[KeyChord]) -> Result<(), String>
+    {
+        let bindings = self.bindings.entry(mode).or_default();
+        let before = bindings.len();
+        bindings.retain(|(bound, _)| bound != keys);
+
+        if bindings.len() == before {
+            return Err(format!("{} is not mapped in {} mode", format_keys(keys), mode.name()));
+        }
+        Ok(())
+    }
+
+    /**
+        Every action bound in a mode with all the keys that run it, in
+        the order they were first bound
+    **/
+    pub fn actions(&self, mode : Mode) -> Vec<(Action, Vec<String>)>
+    {
+        let mut actions : Vec<(Action, Vec<String>)> = Vec::new();
+        for (keys, action) in self.bindings.get(&mode).into_iter().flatten()
+        {
+            let keys = format_keys(keys);
+            match actions.iter_mut().find(|(a, _)| a == action) {
+                Some((_, bound)) => bound.push(keys),
+                None => actions.push((*action, vec![keys])),
+            }
+        }
+        actions
+    }
+
+    pub fn lookup(&self, mode : Mode, keys : &[KeyChord]) -> KeyLookup
+    {
+        let Some(bindings) = self.bindings.get(&mode) else {
+            return KeyLookup::None;
+        };
+
+        if let Some((_, action)) = bindings.iter().find(|(bound, _)| bound == keys) {
+            return KeyLookup::Action(*action);
+        }
+        if bindings.iter().any(|(bound, _)| bound.starts_with(keys)) {
+            return KeyLookup::Pending;
+        }
+        KeyLookup::None
+    }
+
+    /**
+        Applies the [keys] section of the config on top of the defaults,
+        it has a table per mode mapping keys to an action name or "none"
+    **/
+    pub fn apply_config(&mut self, modes : &BTreeMap<String, BTreeMap<String, String>>) -> Result<(), String>
+    {
+        for (mode_name, bindings) in modes
+        {
+            let mode = Mode::from_keymap_name(mode_name).ok_or_else(|| format!("[keys.{}] unknown mode, keys can be bound in {}", mode_name, keymap_mode_names()))?;
+
+            // unmapping goes first so the freed up keys can be reused in the same table
+            let (unmaps, maps) : (Vec<_>, Vec<_>) = bindings.iter().partition(|(_, action)| *action == "none");
+            for (keys, action) in unmaps.into_iter().chain(maps)
+            {
+                let context = |e : String| format!("[keys.{}] \"{}\": {}", mode_name, keys, e);
+                let chords = parse_keys(keys).map_err(context)?;
+
+                if action == "none" {
+                    self.unbind(mode, &chords).map_err(context)?;
+                    continue;
+                }
+                let action = Action::from_name(action).ok_or_else(|| context(format!("unknown action \"{}\"", action)))?;
+                self.bind(mode, chords, action).map_err(context)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn char_key(c : char) -> KeyChord
+    {
+        KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parses_a_sequence()
+    {
+        assert_eq!(parse_keys("gg").unwrap(), vec![char_key('g'), char_key('g')]);
+    }
+
+    #[test]
+    fn parses_bracketed_keys()
+    {
+        assert_eq!(parse_keys("<C-n>").unwrap(), vec![KeyChord::new(KeyCode::Char('n'), KeyModifiers::CONTROL)]);
+        assert_eq!(parse_keys("<space>p").unwrap(), vec![char_key(' '), char_key('p')]);
+        assert_eq!(
+            parse_keys("<C-A-Left>").unwrap(),
+            vec![KeyChord::new(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::ALT)]
+        );
+    }
+
+    #[test]
+    fn a_lone_angle_bracket_is_a_key()
+    {
+        assert_eq!(parse_keys("<").unwrap(), vec![char_key('<')]);
+        assert_eq!(parse_keys("<>").unwrap(), vec![char_key('<'), char_key('>')]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys()
+    {
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("<Nope>").is_err());
+        assert!(parse_keys("<X-a>").is_err());
+    }
+
+    #[test]
+    fn keys_print_the_way_they_parse()
+    {
+        for keys in ["gg", "<C-n>", "<Space>", "<lt>", "G"]
+        {
+            assert_eq!(format_keys(&parse_keys(keys).unwrap()), keys);
+        }
+    }
+
+    #[test]
+    fn bind_rejects_a_prefix_of_a_longer_binding()
+    {
+        let mut keymap = Keymap::default();
+        // gg is bound, g on its own would never get to run
+        let error = keymap.bind(Mode::Normal, parse_keys("g").unwrap(), Action::Quit).unwrap_err();
+        assert!(error.contains("gg"));
+        // and the other way around, r is bound so rx could never be typed
+        assert!(keymap.bind(Mode::Normal, parse_keys("rx").unwrap(), Action::Quit).is_err());
+    }
+
+    #[test]
+    fn bind_replaces_the_same_keys()
+    {
+        let mut keymap = Keymap::default();
+        keymap.bind(Mode::Normal, parse_keys("gg").unwrap(), Action::SelectLast).unwrap();
+        assert!(matches!(keymap.lookup(Mode::Normal, &parse_keys("gg").unwrap()), KeyLookup::Action(Action::SelectLast)));
+    }
+
+    #[test]
+    fn the_start_of_a_sequence_is_pending()
+    {
+        let keymap = Keymap::default();
+        assert!(matches!(keymap.lookup(Mode::Normal, &parse_keys("g").unwrap()), KeyLookup::Pending));
+        assert!(matches!(keymap.lookup(Mode::Normal, &parse_keys("gx").unwrap()), KeyLookup::None));
+    }
+}
@@ -0,0 +1,474 @@
+use serde::Deserialize;
+
+/*
+
+Splits the screen into panes, a layout is a tree of rows and columns where
+every child asks for a size with a constraint, the tree gets resolved into
+one Rect per pane every time the screen is drawn so it reflows on resize
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Rect
+{
+    pub x : u16,
+    pub y : u16,
+    pub width : u16,
+    pub height : u16,
+}
+
+impl Rect
+{
+    pub fn new(x : u16, y : u16, width : u16, height : u16) -> Self
+    {
+        Rect { x, y, width, height }
+    }
+
+    pub fn right(&self) -> u16
+    {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> u16
+    {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, x : u16, y : u16) -> bool
+    {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Constraint
+{
+    Fixed(u16),
+    Percentage(u16),
+    // at least this much, plus a share of whatever is left over
+    Min(u16)
+}
+
+impl Constraint
+{
+    /**
+        Reads "3", "40%" or "min:10"
+    **/
+    pub fn parse(text : &str) -> Result<Self, String>
+    {
+        let text = text.trim();
+        let number = |n : &str| n.trim().parse::<u16>().map_err(|_| format!("invalid size \"{}\"", text));
+
+        if let Some(min) = text.strip_prefix("min:") {
+            Ok(Constraint::Min(number(min)?))
+        } else if let Some(percent) = text.strip_suffix('%') {
+            let percent = number(percent)?;
+            if percent > 100 {
+                return Err(format!("invalid size \"{}\", percentages go up to 100", text));
+            }
+            Ok(Constraint::Percentage(percent))
+        } else {
+            Ok(Constraint::Fixed(number(text)?))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction
+{
+    // children stacked top to bottom
+    Rows,
+    // children side by side left to right
+    Columns
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pane
+{
+    NowPlaying,
+    AlbumArt,
+    Controls,
+    // detail views, or the lyrics when no view is open
+    Library,
+    Queue,
+    Status,
+    // the song with the controls in front of it on a single row
+    MiniPlayer,
+    // a one row bar with the times on either end
+    Progress,
+    // blank space to push panes apart
+    Spacer
+}
+
+// below this many rows the full layout falls over, auto switches to compact
+pub const FULL_MIN_HEIGHT : u16 = 8;
+// the rows the compact layout needs, less than that and auto goes to one line
+pub const COMPACT_HEIGHT : u16 = 3;
+// below this the panes of the full layout start falling over each other
+const FULL_MIN_WIDTH : u16 = 24;
+// the single row layouts get by with room for the controls and a bit of the title
+const SMALL_MIN_WIDTH : u16 = 12;
+
+/**
+        Which layout gets used, full is the one from the config and the
+        smaller ones are for short tmux splits
+**/
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum LayoutPreset
+{
+    // picks one from the height of the terminal
+    #[default]
+    Auto,
+    OneLine,
+    Compact,
+    Full
+}
+
+const PRESETS : &[(&str, LayoutPreset)] = &[
+    ("auto", LayoutPreset::Auto),
+    ("one-line", LayoutPreset::OneLine),
+    ("compact", LayoutPreset::Compact),
+    ("full", LayoutPreset::Full),
+];
+
+impl LayoutPreset
+{
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        PRESETS.iter().find(|(n, _)| *n == name).map(|(_, preset)| *preset)
+    }
+
+    /**
+        The preset to draw with at this height, only auto depends on it
+    **/
+    pub fn pick(&self, height : u16) -> Self
+    {
+        match self
+        {
+            LayoutPreset::Auto if height >= FULL_MIN_HEIGHT => LayoutPreset::Full,
+            LayoutPreset::Auto if height >= COMPACT_HEIGHT => LayoutPreset::Compact,
+            LayoutPreset::Auto => LayoutPreset::OneLine,
+            preset => *preset,
+        }
+    }
+
+    /**
+        The rows a picked preset needs before anything else gets room
+    **/
+    pub fn min_height(&self) -> u16
+    {
+        match self
+        {
+            LayoutPreset::Auto | LayoutPreset::Full => FULL_MIN_HEIGHT,
+            LayoutPreset::Compact => COMPACT_HEIGHT,
+            LayoutPreset::OneLine => 1,
+        }
+    }
+
+    /**
+        The columns a picked preset needs, the smaller ones are meant for
+        narrow tmux splits too
+    **/
+    pub fn min_width(&self) -> u16
+    {
+        match self
+        {
+            LayoutPreset::Auto | LayoutPreset::Full => FULL_MIN_WIDTH,
+            LayoutPreset::Compact | LayoutPreset::OneLine => SMALL_MIN_WIDTH,
+        }
+    }
+}
+
+pub fn layout_preset_names() -> impl Iterator<Item = &'static str>
+{
+    PRESETS.iter().map(|(name, _)| *name)
+}
+
+/**
+        Cuts a length into pieces, fixed and percentage sizes come first and
+        whatever is left gets shared between the min sizes, if there isnt
+        enough room the pieces at the end get squeezed first
+**/
+pub fn split_lengths(total : u16, constraints : &[Constraint]) -> Vec<u16>
+{
+    let mut lengths : Vec<u16> = constraints.iter().map(|c| match c {
+        Constraint::Fixed(n) => *n,
+        Constraint::Percentage(p) => (total as u32 * *p as u32 / 100) as u16,
+        Constraint::Min(n) => *n,
+    }).collect();
+
+    let used : u32 = lengths.iter().map(|l| *l as u32).sum();
+    if used <= total as u32 {
+        let leftover = total - used as u16;
+        let flexible : Vec<usize> = constraints.iter()
+            .enumerate()
+            .filter(|(_, c)| matches!(c, Constraint::Min(_)))
+            .map(|(i, _)| i)
+            .collect();
+
+        if !flexible.is_empty() {
+            let share = leftover / flexible.len() as u16;
+            let mut remainder = leftover % flexible.len() as u16;
+            for i in flexible
+            {
+                lengths[i] += share;
+                if remainder > 0 {
+                    lengths[i] += 1;
+                    remainder -= 1;
+                }
+            }
+        }
+        return lengths;
+    }
+
+    let mut remaining = total;
+    for length in lengths.iter_mut()
+    {
+        *length = (*length).min(remaining);
+        remaining -= *length;
+    }
+    lengths
+}
+
+pub fn split(area : Rect, direction : Direction, constraints : &[Constraint]) -> Vec<Rect>
+{
+    let total = match direction {
+        Direction::Rows => area.height,
+        Direction::Columns => area.width,
+    };
+
+    let mut offset : u16 = 0;
+    split_lengths(total, constraints).into_iter().map(|length| {
+        let rect = match direction {
+            Direction::Rows => Rect::new(area.x, area.y + offset, area.width, length),
+            Direction::Columns => Rect::new(area.x + offset, area.y, length, area.height),
+        };
+        offset += length;
+        rect
+    }).collect()
+}
+
+#[derive(Clone, Debug)]
+pub enum LayoutNode
+{
+    Pane(Pane),
+    Split(Direction, Vec<(Constraint, LayoutNode)>)
+}
+
+impl LayoutNode
+{
+    /**
+        The layout used when the config doesnt set one, the player and
+        controls on the left with the art to the right and the state at the bottom
+    **/
+    pub fn default_layout() -> Self
+    {
+        LayoutNode::Split(Direction::Rows, vec![
+            (Constraint::Min(0), LayoutNode::Split(Direction::Columns, vec![
+                (Constraint::Min(0), LayoutNode::Split(Direction::Rows, vec![
+                    (Constraint::Fixed(3), LayoutNode::Pane(Pane::NowPlaying)),
+                    (Constraint::Fixed(3), LayoutNode::Pane(Pane::Controls)),
+                    (Constraint::Fixed(1), LayoutNode::Pane(Pane::Spacer)),
+                    (Constraint::Min(0), LayoutNode::Pane(Pane::Library)),
+                ])),
+                (Constraint::Percentage(25), LayoutNode::Pane(Pane::AlbumArt)),
+            ])),
+            (Constraint::Fixed(1), LayoutNode::Pane(Pane::Status)),
+        ])
+    }
+
+    /**
+        Three rows, the song with its controls, how far along it is and the state
+    **/
+    pub fn compact_layout() -> Self
+    {
+        LayoutNode::Split(Direction::Rows, vec![
+            (Constraint::Fixed(1), LayoutNode::Pane(Pane::MiniPlayer)),
+            (Constraint::Fixed(1), LayoutNode::Pane(Pane::Progress)),
+            (Constraint::Fixed(1), LayoutNode::Pane(Pane::Status)),
+        ])
+    }
+
+    /**
+        Just the song with its controls, for a split one row tall
+    **/
+    pub fn one_line_layout() -> Self
+    {
+        LayoutNode::Pane(Pane::MiniPlayer)
+    }
+
+    /**
+        Works out the Rect of every pane in the tree for the given area,
+        hidden panes are left out so their neighbours get the space
+    **/
+    pub fn resolve(&self, area : Rect, hidden : &[Pane]) -> Vec<(Pane, Rect)>
+    {
+        let mut panes : Vec<(Pane, Rect)> = Vec::new();
+        self.resolve_into(area, hidden, &mut panes);
+        panes
+    }
+
+    fn resolve_into(&self, area : Rect, hidden : &[Pane], panes : &mut Vec<(Pane, Rect)>)
+    {
+        match self
+        {
+            LayoutNode::Pane(pane) => panes.push((*pane, area)),
+            LayoutNode::Split(direction, children) => {
+                let visible : Vec<&(Constraint, LayoutNode)> = children.iter()
+                    .filter(|(_, child)| !matches!(child, LayoutNode::Pane(p) if hidden.contains(p)))
+                    .collect();
+                let constraints : Vec<Constraint> = visible.iter().map(|(c, _)| *c).collect();
+                let rects = split(area, *direction, &constraints);
+                for ((_, child), rect) in visible.into_iter().zip(rects)
+                {
+                    child.resolve_into(rect, hidden, panes);
+                }
+            }
+        }
+    }
+}
+
+/**
+        A resolved layout, what the tui asks when it needs to know where a pane is
+**/
+pub struct Layout
+{
+    panes : Vec<(Pane, Rect)>,
+}
+
+impl Layout
+{
+    pub fn new(root : &LayoutNode, width : u16, height : u16, hidden : &[Pane]) -> Self
+    {
+        Layout { panes : root.resolve(Rect::new(0, 0, width, height), hidden) }
+    }
+
+    /**
+        Where a pane ended up, None when it isnt in the layout or got no room
+    **/
+    pub fn get(&self, pane : Pane) -> Option<Rect>
+    {
+        self.panes.iter().find(|(p, r)| *p == pane && !r.is_empty()).map(|(_, r)| *r)
+    }
+
+    /**
+        The pane under a point on the screen, used to work out what a click landed on
+    **/
+    pub fn pane_at(&self, x : u16, y : u16) -> Option<(Pane, Rect)>
+    {
+        self.panes.iter().find(|(_, r)| r.contains(x, y)).copied()
+    }
+}
+
+/**
+        How a layout is written in the config file, every node is either a
+        pane or a direction with children and each one can have a size
+**/
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig
+{
+    pub pane : Option<Pane>,
+    pub direction : Option<Direction>,
+    #[serde(default)]
+    pub children : Vec<LayoutConfig>,
+    pub size : Option<String>,
+}
+
+impl LayoutConfig
+{
+    pub fn to_node(&self) -> Result<LayoutNode, String>
+    {
+        match (&self.pane, &self.direction)
+        {
+            (Some(pane), None) => {
+                if !self.children.is_empty() {
+                    return Err("a pane can not have children".to_string());
+                }
+                Ok(LayoutNode::Pane(*pane))
+            }
+            (None, Some(direction)) => {
+                if self.children.is_empty() {
+                    return Err("a split needs at least one child".to_string());
+                }
+                let mut children : Vec<(Constraint, LayoutNode)> = Vec::new();
+                for child in &self.children
+                {
+                    let constraint = match &child.size {
+                        Some(size) => Constraint::parse(size)?,
+                        None => Constraint::Min(0),
+                    };
+                    children.push((constraint, child.to_node()?));
+                }
+                Ok(LayoutNode::Split(*direction, children))
+            }
+            (Some(_), Some(_)) => Err("a layout node is either a pane or a direction, not both".to_string()),
+            (None, None) => Err("a layout node needs a pane or a direction".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn nothing_to_split()
+    {
+        assert_eq!(split_lengths(0, &[Constraint::Fixed(3), Constraint::Min(0), Constraint::Percentage(50)]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn fixed_sizes_past_the_total_squeeze_the_end()
+    {
+        assert_eq!(split_lengths(7, &[Constraint::Fixed(3), Constraint::Fixed(3), Constraint::Fixed(3)]), vec![3, 3, 1]);
+        assert_eq!(split_lengths(2, &[Constraint::Fixed(3), Constraint::Min(1)]), vec![2, 0]);
+    }
+
+    #[test]
+    fn whole_and_empty_percentages()
+    {
+        assert_eq!(split_lengths(50, &[Constraint::Percentage(100), Constraint::Percentage(0)]), vec![50, 0]);
+        assert_eq!(split_lengths(50, &[Constraint::Percentage(0), Constraint::Min(0)]), vec![0, 50]);
+    }
+
+    #[test]
+    fn percentages_round_down()
+    {
+        assert_eq!(split_lengths(9, &[Constraint::Percentage(50), Constraint::Percentage(50)]), vec![4, 4]);
+    }
+
+    #[test]
+    fn uneven_min_split_gives_the_remainder_to_the_first()
+    {
+        assert_eq!(split_lengths(10, &[Constraint::Min(0), Constraint::Min(0), Constraint::Min(0)]), vec![4, 3, 3]);
+        assert_eq!(split_lengths(10, &[Constraint::Fixed(1), Constraint::Min(2), Constraint::Min(0)]), vec![1, 6, 3]);
+    }
+
+    #[test]
+    fn parses_constraints()
+    {
+        assert_eq!(Constraint::parse("3"), Ok(Constraint::Fixed(3)));
+        assert_eq!(Constraint::parse(" 40% "), Ok(Constraint::Percentage(40)));
+        assert_eq!(Constraint::parse("100%"), Ok(Constraint::Percentage(100)));
+        assert_eq!(Constraint::parse("0%"), Ok(Constraint::Percentage(0)));
+        assert_eq!(Constraint::parse("min:10"), Ok(Constraint::Min(10)));
+    }
+
+    #[test]
+    fn rejects_bad_constraints()
+    {
+        assert!(Constraint::parse("101%").is_err());
+        assert!(Constraint::parse("-1").is_err());
+        assert!(Constraint::parse("min:").is_err());
+        assert!(Constraint::parse("wide").is_err());
+    }
+}
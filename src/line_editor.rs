@@ -0,0 +1,319 @@
+use std::{
+    fs,
+    io,
+    path::PathBuf
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::text;
+
+/*
+
+The line you type after a :, works like the vim command line with cursor
+movement, word motions, a history that is kept between runs and tab
+completion fed by whoever owns the editor
+
+*/
+
+// older lines fall off the history once it gets this long
+const HISTORY_LIMIT : usize = 500;
+
+pub enum EditResult
+{
+    // still typing
+    Editing,
+    Submit(String),
+    Cancel,
+    // the owner should work out candidates and hand them to complete
+    Complete { backwards : bool }
+}
+
+/**
+        Candidates being cycled through with tab, they replace the text
+        from start up to wherever the last candidate ended
+**/
+struct Completion
+{
+    start : usize,
+    end : usize,
+    candidates : Vec<String>,
+    index : usize,
+}
+
+pub struct LineEditor
+{
+    text : String,
+    // byte offset into text, always on a grapheme boundary
+    cursor : usize,
+    history : Vec<String>,
+    history_path : PathBuf,
+    // where we are while going through the history with up and down
+    history_index : Option<usize>,
+    // what was typed before going into the history, up and down only
+    // show entries starting with it and it comes back at the bottom
+    draft : String,
+    completion : Option<Completion>,
+}
+
+impl LineEditor
+{
+    /**
+        Starts empty with the history from history_path, a missing file is an empty history
+    **/
+    pub fn new(history_path : PathBuf) -> Self
+    {
+        let history = fs::read_to_string(&history_path)
+            .map(|h| h.lines().filter(|l| !l.is_empty()).map(|l| l.to_string()).collect())
+            .unwrap_or_default();
+
+        LineEditor {
+            text : String::new(),
+            cursor : 0,
+            history,
+            history_path,
+            history_index : None,
+            draft : String::new(),
+            completion : None,
+        }
+    }
+
+    pub fn open(&mut self)
+    {
+        self.set_text(String::new());
+        self.history_index = None;
+    }
+
+    pub fn close(&mut self)
+    {
+        self.completion = None;
+    }
+
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    /**
+        Everything left of the cursor, what completion works off
+    **/
+    pub fn before_cursor(&self) -> &str
+    {
+        &self.text[..self.cursor]
+    }
+
+    /**
+        How many columns in from the start of the text the cursor sits
+    **/
+    pub fn cursor_width(&self) -> usize
+    {
+        text::display_width(self.before_cursor())
+    }
+
+    fn set_text(&mut self, text : String)
+    {
+        self.cursor = text.len();
+        self.text = text;
+        self.completion = None;
+    }
+
+    fn previous_boundary(&self) -> usize
+    {
+        self.before_cursor().grapheme_indices(true).next_back().map(|(i, _)| i).unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize
+    {
+        self.text[self.cursor..].graphemes(true).next().map(|g| self.cursor + g.len()).unwrap_or(self.cursor)
+    }
+
+    /**
+        The start of the word left of the cursor, skipping any spaces first like vim does
+    **/
+    fn previous_word(&self) -> usize
+    {
+        let before = self.before_cursor().trim_end();
+        before.rfind(char::is_whitespace).map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn next_word(&self) -> usize
+    {
+        let after = &self.text[self.cursor..];
+        let word_end = after.find(char::is_whitespace).unwrap_or(after.len());
+        let next_start = after[word_end..].find(|c : char| !c.is_whitespace()).map(|i| word_end + i).unwrap_or(after.len());
+        self.cursor + next_start
+    }
+
+    pub fn handle_key(&mut self, event : &KeyEvent) -> EditResult
+    {
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+
+        // anything but another tab means the completion was taken
+        if !matches!(event.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.completion = None;
+        }
+
+        match event.code
+        {
+            KeyCode::Esc => return EditResult::Cancel,
+            KeyCode::Char('c') if control => return EditResult::Cancel,
+            KeyCode::Enter => {
+                let line = self.text.clone();
+                self.push_history(&line);
+                return EditResult::Submit(line);
+            }
+            KeyCode::Tab => return EditResult::Complete { backwards : false },
+            KeyCode::BackTab => return EditResult::Complete { backwards : true },
+
+            // backspace on an empty line leaves the command line like in vim
+            KeyCode::Backspace if self.text.is_empty() => return EditResult::Cancel,
+            KeyCode::Backspace => {
+                let start = self.previous_boundary();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Char('w') if control => {
+                let start = self.previous_word();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Char('u') if control => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+
+            KeyCode::Left if control => self.cursor = self.previous_word(),
+            KeyCode::Right if control => self.cursor = self.next_word(),
+            KeyCode::Char('b') if alt => self.cursor = self.previous_word(),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('b') if control => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Char('e') if control => self.cursor = self.text.len(),
+
+            KeyCode::Up => self.history_previous(),
+            KeyCode::Down => self.history_next(),
+
+            KeyCode::Char(c) if !control && !alt => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            _ => {}
+        }
+
+        // typing over a line from the history makes it the new draft
+        if !matches!(event.code, KeyCode::Up | KeyCode::Down) {
+            self.history_index = None;
+        }
+        EditResult::Editing
+    }
+
+    fn history_previous(&mut self)
+    {
+        let end = match self.history_index {
+            Some(index) => index,
+            None => {
+                self.draft = self.text.clone();
+                self.history.len()
+            }
+        };
+
+        if let Some(index) = (0..end).rev().find(|i| self.history[*i].starts_with(&self.draft)) {
+            self.history_index = Some(index);
+            self.set_text(self.history[index].clone());
+        }
+    }
+
+    fn history_next(&mut self)
+    {
+        let Some(current) = self.history_index else {
+            return;
+        };
+
+        match (current + 1..self.history.len()).find(|i| self.history[*i].starts_with(&self.draft)) {
+            Some(index) => {
+                self.history_index = Some(index);
+                self.set_text(self.history[index].clone());
+            }
+            None => {
+                self.history_index = None;
+                self.set_text(self.draft.clone());
+            }
+        }
+    }
+
+    fn push_history(&mut self, line : &str)
+    {
+        if line.trim().is_empty() || self.history.last().is_some_and(|l| l == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.drain(..self.history.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /**
+        Writes the history out so the next run can scroll back through it
+    **/
+    pub fn save_history(&self) -> io::Result<()>
+    {
+        if let Some(dir) = self.history_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = self.history.join("\n");
+        contents.push('\n');
+        fs::write(&self.history_path, contents)
+    }
+
+    /**
+        True while tab is cycling through candidates, the owner only has to
+        work out new ones when this is false
+    **/
+    pub fn is_completing(&self) -> bool
+    {
+        self.completion.is_some()
+    }
+
+    /**
+        Starts cycling through candidates for the text between start and the
+        cursor, with a single candidate it just gets filled in
+    **/
+    pub fn start_completion(&mut self, start : usize, candidates : Vec<String>, backwards : bool)
+    {
+        if candidates.is_empty() {
+            return;
+        }
+        let index = if backwards { candidates.len() - 1 } else { 0 };
+        self.completion = Some(Completion { start, end : self.cursor, candidates, index });
+        self.apply_completion();
+    }
+
+    pub fn cycle_completion(&mut self, backwards : bool)
+    {
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+        let count = completion.candidates.len();
+        completion.index = if backwards { (completion.index + count - 1) % count } else { (completion.index + 1) % count };
+        self.apply_completion();
+    }
+
+    fn apply_completion(&mut self)
+    {
+        let Some(completion) = &mut self.completion else {
+            return;
+        };
+        let candidate = &completion.candidates[completion.index];
+        self.text.replace_range(completion.start..completion.end, candidate);
+        completion.end = completion.start + candidate.len();
+        self.cursor = completion.end;
+    }
+}
@@ -0,0 +1,41 @@
+/*
+
+The time on the wall clock, for the status bar clock and the timestamps in
+the log, libc does the timezone work so there is no date crate to pull in
+
+*/
+
+pub struct LocalTime
+{
+    pub year : i32,
+    // 1 to 12
+    pub month : u32,
+    pub day : u32,
+    pub hour : u32,
+    pub minute : u32,
+    pub second : u32,
+}
+
+impl LocalTime
+{
+    pub fn now() -> Self
+    {
+        let mut now : libc::time_t = 0;
+        // localtime_r fills in the tm we hand it so it is fine from any thread
+        let local = unsafe {
+            let mut local : libc::tm = std::mem::zeroed();
+            libc::time(&mut now);
+            libc::localtime_r(&now, &mut local);
+            local
+        };
+
+        LocalTime {
+            year : local.tm_year + 1900,
+            month : local.tm_mon as u32 + 1,
+            day : local.tm_mday as u32,
+            hour : local.tm_hour as u32,
+            minute : local.tm_min as u32,
+            second : local.tm_sec as u32,
+        }
+    }
+}
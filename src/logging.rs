@@ -0,0 +1,326 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex
+};
+
+use super::local_time::LocalTime;
+use super::paths;
+
+/*
+
+The log file, every line gets a timestamp, a level and the module it came
+from and goes to comfyfy.log in the state dir, which rolls over to
+comfyfy.log.1 and on once it gets big, anything that looks like a secret
+is blanked out before it is written
+
+*/
+
+// the file rolls over once it would go past this
+const MAX_LOG_BYTES : u64 = 1024 * 1024;
+// how many rolled over files are kept, comfyfy.log.1 is the newest
+const KEEP_LOGS : usize = 3;
+const REDACTED : &str = "[redacted]";
+// whatever follows these gets blanked out, like access_token=abc or "refresh_token": "abc"
+const SECRET_KEYS : &[&str] = &["access_token", "refresh_token", "client_secret", "bearer"];
+// COMFYFY_LOG=debug does the same as --log-level debug
+const LOG_ENV : &str = "COMFYFY_LOG";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+pub enum Level
+{
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace
+}
+
+const LEVELS : &[(&str, Level)] = &[
+    ("off", Level::Off),
+    ("error", Level::Error),
+    ("warn", Level::Warn),
+    ("info", Level::Info),
+    ("debug", Level::Debug),
+    ("trace", Level::Trace),
+];
+
+impl Level
+{
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        LEVELS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, level)| *level)
+    }
+
+    fn label(&self) -> &'static str
+    {
+        match self
+        {
+            Level::Off => "OFF",
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+
+    /**
+        The level in COMFYFY_LOG, None when it isnt set
+    **/
+    pub fn from_env() -> Result<Option<Self>, String>
+    {
+        match std::env::var(LOG_ENV) {
+            Ok(name) => Level::from_name(&name)
+                .map(Some)
+                .ok_or_else(|| format!("{}: unknown log level \"{}\", the levels are {}", LOG_ENV, name, level_names().collect::<Vec<_>>().join(", "))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+pub fn level_names() -> impl Iterator<Item = &'static str>
+{
+    LEVELS.iter().map(|(name, _)| *name)
+}
+
+struct Logger
+{
+    level : Level,
+    path : PathBuf,
+    // opened on the first line, None again after rolling over
+    file : Option<File>,
+    size : u64,
+    // the tokens themselves, blanked out even where no key gives them away
+    secrets : Vec<String>,
+}
+
+static LOGGER : Mutex<Option<Logger>> = Mutex::new(None);
+
+/**
+        Starts logging at level, nothing gets written before this
+**/
+pub fn init(level : Level)
+{
+    if let Ok(mut logger) = LOGGER.lock() {
+        *logger = Some(Logger {
+            level,
+            path : paths::state_dir().join("comfyfy.log"),
+            file : None,
+            size : 0,
+            secrets : Vec::new(),
+        });
+    }
+}
+
+/**
+        Remembers a token or a secret so it never makes it into the log as is
+**/
+pub fn add_secret(secret : &str)
+{
+    // anything this short would blank out ordinary words
+    if secret.len() < 8 {
+        return;
+    }
+    if let Ok(mut logger) = LOGGER.lock() {
+        if let Some(logger) = logger.as_mut() {
+            if !logger.secrets.iter().any(|s| s == secret) {
+                logger.secrets.push(secret.to_string());
+            }
+        }
+    }
+}
+
+/**
+        Writes a line if the level is on, what the macros below end up calling,
+        a log that cant be written is dropped since there is nowhere to report it
+**/
+pub fn log(level : Level, target : &str, message : &str)
+{
+    let Ok(mut logger) = LOGGER.lock() else {
+        return;
+    };
+    let Some(logger) = logger.as_mut() else {
+        return;
+    };
+    if level == Level::Off || level > logger.level {
+        return;
+    }
+
+    let now = LocalTime::now();
+    let line = format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02} {:<5} {}: {}\n",
+        now.year, now.month, now.day, now.hour, now.minute, now.second,
+        level.label(),
+        target,
+        redact(message, &logger.secrets)
+    );
+    let _ = logger.write(&line);
+}
+
+impl Logger
+{
+    fn write(&mut self, line : &str) -> std::io::Result<()>
+    {
+        if self.file.is_some() && self.size + line.len() as u64 > MAX_LOG_BYTES {
+            self.roll_over()?;
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                if let Some(dir) = self.path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+                self.size = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+
+        file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /**
+        comfyfy.log becomes comfyfy.log.1, .1 becomes .2 and so on, the
+        oldest one falls off the end
+    **/
+    fn roll_over(&mut self) -> std::io::Result<()>
+    {
+        self.file = None;
+        let rolled = |n : usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+        for n in (1..KEEP_LOGS).rev()
+        {
+            if rolled(n).exists() {
+                fs::rename(rolled(n), rolled(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rolled(1))
+    }
+}
+
+/**
+        Swaps every known secret and every value after a secret key for [redacted]
+**/
+fn redact(message : &str, secrets : &[String]) -> String
+{
+    let mut redacted = message.to_string();
+    for secret in secrets
+    {
+        redacted = redacted.replace(secret.as_str(), REDACTED);
+    }
+    for key in SECRET_KEYS
+    {
+        redacted = redact_after(&redacted, key);
+    }
+    redacted
+}
+
+/**
+        Blanks out the value after each time key shows up, the key and
+        whatever sits between it and the value like quotes, : or = stay
+**/
+fn redact_after(text : &str, key : &str) -> String
+{
+    // only ascii gets lowered so the byte offsets still line up with text
+    let lower = text.to_ascii_lowercase();
+    let mut redacted = String::new();
+    let mut copied = 0;
+    let mut search = 0;
+
+    while let Some(found) = lower[search..].find(key)
+    {
+        let key_end = search + found + key.len();
+        let value_start = text[key_end..]
+            .find(|c : char| !matches!(c, '"' | '\'' | ':' | '=' | ' '))
+            .map_or(text.len(), |i| key_end + i);
+        let value_end = text[value_start..]
+            .find(|c : char| c.is_whitespace() || matches!(c, '"' | '\'' | ',' | '&' | '}' | ']'))
+            .map_or(text.len(), |i| value_start + i);
+        search = key_end;
+
+        // a word that only starts with the key, or a value that is already gone
+        if value_start == key_end || value_start == value_end || &text[value_start..value_end] == REDACTED {
+            continue;
+        }
+
+        redacted.push_str(&text[copied..value_start]);
+        redacted.push_str(REDACTED);
+        copied = value_end;
+        search = value_end;
+    }
+
+    redacted.push_str(&text[copied..]);
+    redacted
+}
+
+macro_rules! error {
+    ($($arg : tt)*) => { $crate::logging::log($crate::logging::Level::Error, module_path!(), &format!($($arg)*)) };
+}
+
+// named warn once it is exported, a macro_rules called warn clashes with the warn attribute
+macro_rules! warn_line {
+    ($($arg : tt)*) => { $crate::logging::log($crate::logging::Level::Warn, module_path!(), &format!($($arg)*)) };
+}
+
+macro_rules! info {
+    ($($arg : tt)*) => { $crate::logging::log($crate::logging::Level::Info, module_path!(), &format!($($arg)*)) };
+}
+
+macro_rules! debug {
+    ($($arg : tt)*) => { $crate::logging::log($crate::logging::Level::Debug, module_path!(), &format!($($arg)*)) };
+}
+
+pub(crate) use {debug, error, info, warn_line as warn};
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn redacts_json_values()
+    {
+        let line = r#"{"access_token":"BQDa1b2c3","token_type":"Bearer","expires_in":3600}"#;
+        assert_eq!(
+            redact(line, &[]),
+            r#"{"access_token":"[redacted]","token_type":"Bearer","expires_in":3600}"#
+        );
+    }
+
+    #[test]
+    fn redacts_query_strings()
+    {
+        let line = "POST /api/token?grant_type=refresh_token&refresh_token=AQC9x8y7&client_secret=f00ba4 sent";
+        assert_eq!(
+            redact(line, &[]),
+            "POST /api/token?grant_type=refresh_token&refresh_token=[redacted]&client_secret=[redacted] sent"
+        );
+    }
+
+    #[test]
+    fn redacts_bearer_headers()
+    {
+        assert_eq!(redact("Authorization: Bearer BQDa1b2c3", &[]), "Authorization: Bearer [redacted]");
+    }
+
+    #[test]
+    fn redacts_a_key_every_time()
+    {
+        let line = "access_token=first access_token=second";
+        assert_eq!(redact(line, &[]), "access_token=[redacted] access_token=[redacted]");
+    }
+
+    #[test]
+    fn redacts_known_secrets_without_a_key()
+    {
+        let secrets = vec!["BQDa1b2c3d4".to_string()];
+        assert_eq!(redact("token is BQDa1b2c3d4 now", &secrets), "token is [redacted] now");
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    error::Error,
+    fs,
+    path::PathBuf
+};
+
+use super::paths;
+
+/*
+
+Synced lyrics, providers hand back raw LRC text for an artist and title,
+the manager caches whatever they find on disk and parses it into lines
+that can be matched against the playback progress
+
+*/
+
+/**
+        Anything that can find lyrics for a track, returns the LRC text
+        or None when it doesnt have them, fetch runs on a blocking thread
+        away from the ui so it is free to read files or wait on the network
+**/
+pub trait LyricsProvider : Send + Sync
+{
+    fn name(&self) -> &str;
+    fn fetch(&self, artist : &str, title : &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>>;
+}
+
+pub struct LyricLine
+{
+    pub time_ms : u64,
+    pub text : String,
+}
+
+pub struct Lyrics
+{
+    pub lines : Vec<LyricLine>,
+    // from the [offset:] tag, positive means the lyrics show up sooner
+    pub offset_ms : i64,
+    // plain text lyrics without any timestamps cant be highlighted
+    pub synced : bool,
+}
+
+/**
+        Parses a single [mm:ss.xx] timestamp into milliseconds
+**/
+fn parse_timestamp(tag : &str) -> Option<u64>
+{
+    let (minutes, rest) = tag.split_once(':')?;
+    let minutes : u64 = minutes.trim().parse().ok()?;
+
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (rest, ""),
+    };
+    let seconds : u64 = seconds.trim().parse().ok()?;
+
+    // checked first so the slicing below never lands inside a multibyte character
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // .x is tenths .xx is hundredths and .xxx is milliseconds
+    let fraction_ms : u64 = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction.get(..3)?.parse().ok()?,
+    };
+
+    Some(minutes * 60_000 + seconds * 1000 + fraction_ms)
+}
+
+impl Lyrics
+{
+    pub fn parse(text : &str) -> Self
+    {
+        let mut lines : Vec<LyricLine> = Vec::new();
+        let mut plain : Vec<String> = Vec::new();
+        let mut offset_ms : i64 = 0;
+
+        for raw_line in text.lines()
+        {
+            let mut rest = raw_line.trim();
+            let mut times : Vec<u64> = Vec::new();
+
+            // a line can carry several timestamps when a chorus repeats
+            while rest.starts_with('[')
+            {
+                let Some(end) = rest.find(']') else { break };
+                let tag = &rest[1..end];
+
+                if let Some(time_ms) = parse_timestamp(tag) {
+                    times.push(time_ms);
+                } else if let Some(offset) = tag.strip_prefix("offset:") {
+                    offset_ms = offset.trim().parse().unwrap_or(0);
+                }
+                // other id tags like [ar:] and [ti:] are skipped
+
+                rest = rest[end + 1..].trim_start();
+            }
+
+            if times.is_empty() {
+                if !raw_line.trim().starts_with('[') {
+                    plain.push(raw_line.trim().to_string());
+                }
+                continue;
+            }
+
+            for time_ms in times
+            {
+                lines.push(LyricLine { time_ms, text : rest.to_string() });
+            }
+        }
+
+        if lines.is_empty() {
+            return Lyrics {
+                lines : plain.into_iter().map(|text| LyricLine { time_ms : 0, text }).collect(),
+                offset_ms,
+                synced : false,
+            };
+        }
+
+        lines.sort_by_key(|l| l.time_ms);
+        Lyrics { lines, offset_ms, synced : true }
+    }
+
+    /**
+        The line that should be highlighted at this point of the track
+    **/
+    pub fn line_at(&self, progress_ms : u64) -> Option<usize>
+    {
+        if !self.synced {
+            return None;
+        }
+
+        let adjusted_ms = progress_ms as i64 + self.offset_ms;
+        if adjusted_ms < 0 {
+            return None;
+        }
+
+        let passed = self.lines.partition_point(|l| l.time_ms <= adjusted_ms as u64);
+        passed.checked_sub(1)
+    }
+}
+
+/**
+        Looks for .lrc files in a directory, either laid out as
+        "Artist - Title.lrc" or "Artist/Title.lrc", ignoring case
+**/
+pub struct LocalDirectoryProvider
+{
+    directory : PathBuf,
+}
+
+impl LocalDirectoryProvider
+{
+    pub fn new(directory : PathBuf) -> Self
+    {
+        LocalDirectoryProvider { directory }
+    }
+
+    fn find_case_insensitive(directory : &PathBuf, file_name : &str) -> Option<PathBuf>
+    {
+        let wanted = file_name.to_lowercase();
+        fs::read_dir(directory).ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.to_lowercase() == wanted))
+    }
+}
+
+impl LyricsProvider for LocalDirectoryProvider
+{
+    fn name(&self) -> &str
+    {
+        "local"
+    }
+
+    fn fetch(&self, artist : &str, title : &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>>
+    {
+        let artist = paths::sanitize_file_name(artist);
+        let title = paths::sanitize_file_name(title);
+
+        let flat = LocalDirectoryProvider::find_case_insensitive(&self.directory, &format!("{} - {}.lrc", artist, title));
+        let nested = || {
+            let artist_dir = LocalDirectoryProvider::find_case_insensitive(&self.directory, &artist)?;
+            LocalDirectoryProvider::find_case_insensitive(&artist_dir, &format!("{}.lrc", title))
+        };
+
+        match flat.or_else(nested) {
+            Some(path) => Ok(Some(fs::read_to_string(path)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/**
+        Asks every provider in order and keeps the first hit in the on disk cache
+**/
+pub struct LyricsManager
+{
+    providers : Vec<Box<dyn LyricsProvider>>,
+    cache_dir : PathBuf,
+}
+
+impl LyricsManager
+{
+    pub fn new(cache_dir : PathBuf) -> Self
+    {
+        LyricsManager { providers : Vec::new(), cache_dir }
+    }
+
+    /**
+        Sets up the local provider from LYRICS_DIR in the .env, whatever gets
+        found is cached under ~/.cache/comfyfy/lyrics
+    **/
+    pub fn from_env() -> Self
+    {
+        let mut manager = LyricsManager::new(paths::cache_dir().join("lyrics"));
+
+        if let Some(directory) = std::env::var("LYRICS_DIR").ok().filter(|d| !d.is_empty()) {
+            manager.add_provider(Box::new(LocalDirectoryProvider::new(PathBuf::from(directory))));
+        }
+        manager
+    }
+
+    pub fn add_provider(&mut self, provider : Box<dyn LyricsProvider>)
+    {
+        self.providers.push(provider);
+    }
+
+    fn cache_path(&self, artist : &str, title : &str) -> PathBuf
+    {
+        self.cache_dir.join(format!("{} - {}.lrc", paths::sanitize_file_name(artist), paths::sanitize_file_name(title)))
+    }
+
+    /**
+        Finds lyrics for a track, a failing provider doesnt stop the rest
+        from being asked, its error only comes back if nobody had them
+    **/
+    pub fn lookup(&self, artist : &str, title : &str) -> Result<Option<Lyrics>, Box<dyn Error + Send + Sync>>
+    {
+        let cache_path = self.cache_path(artist, title);
+        if let Ok(cached) = fs::read_to_string(&cache_path) {
+            return Ok(Some(Lyrics::parse(&cached)));
+        }
+
+        let mut errors : Vec<String> = Vec::new();
+        let mut found : Option<String> = None;
+        for provider in &self.providers
+        {
+            match provider.fetch(artist, title) {
+                Ok(Some(text)) => {
+                    found = Some(text);
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{}: {}", provider.name(), e)),
+            }
+        }
+
+        match found {
+            Some(text) => {
+                fs::create_dir_all(&self.cache_dir)?;
+                fs::write(&cache_path, &text)?;
+                Ok(Some(Lyrics::parse(&text)))
+            }
+            None if !errors.is_empty() => Err(errors.join(", ").into()),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parses_timestamps_with_hundredths()
+    {
+        assert_eq!(parse_timestamp("01:02.34"), Some(62_340));
+        assert_eq!(parse_timestamp("00:05.5"), Some(5_500));
+        assert_eq!(parse_timestamp("00:05.123"), Some(5_123));
+    }
+
+    #[test]
+    fn parses_timestamps_without_a_fraction()
+    {
+        assert_eq!(parse_timestamp("02:03"), Some(123_000));
+    }
+
+    #[test]
+    fn rejects_timestamps_that_arent_numbers()
+    {
+        assert_eq!(parse_timestamp("ar:Someone"), None);
+        assert_eq!(parse_timestamp("00:01.é5"), None);
+    }
+
+    #[test]
+    fn a_line_with_several_timestamps_shows_up_at_each()
+    {
+        let lyrics = Lyrics::parse("[00:10.00][00:30.00]Chorus\n[00:20.00]Verse");
+        let lines : Vec<(u64, &str)> = lyrics.lines.iter().map(|l| (l.time_ms, l.text.as_str())).collect();
+        assert_eq!(lines, vec![(10_000, "Chorus"), (20_000, "Verse"), (30_000, "Chorus")]);
+        assert!(lyrics.synced);
+    }
+
+    #[test]
+    fn a_positive_offset_shows_lines_sooner()
+    {
+        let lyrics = Lyrics::parse("[offset:+500]\n[00:01.00]One\n[00:02.00]Two");
+        assert_eq!(lyrics.offset_ms, 500);
+        assert_eq!(lyrics.line_at(1_500), Some(1));
+    }
+
+    #[test]
+    fn a_negative_offset_shows_lines_later()
+    {
+        let lyrics = Lyrics::parse("[offset:-500]\n[00:01.00]One\n[00:02.00]Two");
+        assert_eq!(lyrics.offset_ms, -500);
+        assert_eq!(lyrics.line_at(2_200), Some(0));
+        assert_eq!(lyrics.line_at(200), None);
+    }
+
+    #[test]
+    fn no_line_before_the_first_one()
+    {
+        let lyrics = Lyrics::parse("[00:05.00]One\n[00:10.00]Two");
+        assert_eq!(lyrics.line_at(0), None);
+        assert_eq!(lyrics.line_at(4_999), None);
+        assert_eq!(lyrics.line_at(5_000), Some(0));
+        assert_eq!(lyrics.line_at(60_000), Some(1));
+    }
+
+    #[test]
+    fn plain_text_isnt_synced()
+    {
+        let lyrics = Lyrics::parse("[ar:Someone]\nJust words\nMore words");
+        assert!(!lyrics.synced);
+        assert_eq!(lyrics.lines.len(), 2);
+        assert_eq!(lyrics.line_at(1_000), None);
+    }
+}
@@ -1,17 +1,38 @@
 
-/** 
+/*! 
 
     Project Name : Comfyfy
     Description  : Terminal TUI app to listen to music
 
 **/
 
+mod album_art;
+mod args;
+mod commands;
+mod config;
+mod keymap;
+mod layout;
+mod line_editor;
+mod local_time;
+mod logging;
+mod lyrics;
+mod mode;
+mod notifications;
+mod paths;
+mod renderer;
 mod spotify_client_auth;
+mod status_bar;
+mod terminal;
+mod text;
+mod theme;
 mod tui;
+mod views;
 
 use dotenv::dotenv;
 use spotify_client_auth::SpotifyClientAuth;
 use tui::Tui;
+use config::Config;
+use args::Args;
 
 
 #[tokio::main]
@@ -19,17 +40,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>
 {
     dotenv().ok();
 
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return Ok(());
+        }
+    };
+
+    // a broken config should be fixed before anything else happens
+    let mut config : Config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ Invalid config {}", e);
+            return Ok(());
+        }
+    };
+    if let Some(layout) = args.layout {
+        config.layout_preset = layout;
+    }
+
+    // --log-level wins over COMFYFY_LOG
+    let log_level = match args.log_level.map(|level| Ok(Some(level))).unwrap_or_else(logging::Level::from_env) {
+        Ok(level) => level.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return Ok(());
+        }
+    };
+    logging::init(log_level);
+    logging::info!("starting comfyfy {}", env!("CARGO_PKG_VERSION"));
+
     // start a new instance of SpotifyClientAuth
-    let mut auth : SpotifyClientAuth = SpotifyClientAuth::new().await.unwrap();
+    let auth : SpotifyClientAuth = SpotifyClientAuth::new().await.unwrap();
 
-    if let Err(_) = auth.get_token().await {
+    if auth.get_token().await.is_err() {
         eprintln!("❌ Failed to authenticate");
         return Ok(());
     }
 
 
     // want to start the TUI here
-    let mut tui : Tui = Tui::new(auth);
+    let mut tui : Tui = Tui::new(auth, config);
     tui.start().await?;
 
     Ok(())
@@ -0,0 +1,139 @@
+/*
+
+The modes the tui can be in, kept as a stack with normal mode always at
+the bottom, opening something pushes its mode and closing it pops back to
+whatever was underneath, esc drops straight back down to normal
+
+*/
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Mode
+{
+    Normal,
+    // typing after a :
+    Command,
+    // typing after a /
+    Search,
+    // picking a range of rows in a view
+    Visual,
+    Help,
+    Popup
+}
+
+// the modes that look their keys up in the keymap, command and search send theirs to a line editor
+pub const KEYMAP_MODES : [Mode; 4] = [Mode::Normal, Mode::Visual, Mode::Help, Mode::Popup];
+
+impl Mode
+{
+    /**
+        What the mode is called in the [keys] config and :map
+    **/
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Mode::Normal => "normal",
+            Mode::Command => "command",
+            Mode::Search => "search",
+            Mode::Visual => "visual",
+            Mode::Help => "help",
+            Mode::Popup => "popup",
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        match name
+        {
+            "normal" => Some(Mode::Normal),
+            "command" => Some(Mode::Command),
+            "search" => Some(Mode::Search),
+            "visual" => Some(Mode::Visual),
+            "help" => Some(Mode::Help),
+            "popup" => Some(Mode::Popup),
+            _ => None,
+        }
+    }
+
+    /**
+        Only the modes in KEYMAP_MODES, the ones keys can be bound in
+    **/
+    pub fn from_keymap_name(name : &str) -> Option<Self>
+    {
+        Mode::from_name(name).filter(|m| KEYMAP_MODES.contains(m))
+    }
+
+    /**
+        Shown on the state line while the mode is active
+    **/
+    pub fn indicator(&self) -> &'static str
+    {
+        match self
+        {
+            Mode::Normal => "NORMAL",
+            Mode::Command => "COMMAND",
+            Mode::Search => "SEARCH",
+            Mode::Visual => "VISUAL",
+            Mode::Help => "HELP",
+            Mode::Popup => "POPUP",
+        }
+    }
+}
+
+pub struct ModeStack
+{
+    // never empty, the first one is always normal
+    stack : Vec<Mode>,
+}
+
+impl ModeStack
+{
+    pub fn new() -> Self
+    {
+        ModeStack { stack : vec![Mode::Normal] }
+    }
+
+    pub fn current(&self) -> Mode
+    {
+        *self.stack.last().unwrap_or(&Mode::Normal)
+    }
+
+    /**
+        Enters a mode on top of the current one, a mode that is already
+        on the stack gets brought back up instead of going on twice
+    **/
+    pub fn push(&mut self, mode : Mode)
+    {
+        if mode == Mode::Normal {
+            self.stack.truncate(1);
+            return;
+        }
+        self.stack.retain(|m| *m != mode);
+        self.stack.push(mode);
+    }
+
+    /**
+        Leaves mode, along with anything opened on top of it, returns the
+        modes that were left so whatever they had open can be closed
+    **/
+    pub fn leave(&mut self, mode : Mode) -> Vec<Mode>
+    {
+        match self.stack.iter().skip(1).position(|m| *m == mode) {
+            Some(index) => self.stack.split_off(index + 1),
+            None => Vec::new(),
+        }
+    }
+
+    /**
+        Straight back to normal, what esc does
+    **/
+    pub fn reset(&mut self) -> Vec<Mode>
+    {
+        self.stack.split_off(1)
+    }
+
+    pub fn contains(&self, mode : Mode) -> bool
+    {
+        self.stack.contains(&mode)
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant}
+};
+
+/*
+
+Short lived messages for things the user did or that went wrong, they
+stack up at the bottom and go away on their own, nothing waits on them so
+the ui never stops to show one
+
+*/
+
+// more than this on screen and the oldest ones make room
+pub const MAX_VISIBLE : usize = 4;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Level
+{
+    Info,
+    Warn,
+    Error
+}
+
+impl Level
+{
+    /**
+        Errors stay up the longest since they are the ones that need reading
+    **/
+    fn duration(&self) -> Duration
+    {
+        match self
+        {
+            Level::Info => Duration::from_secs(4),
+            Level::Warn => Duration::from_secs(6),
+            Level::Error => Duration::from_secs(8),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str
+    {
+        match self
+        {
+            Level::Info => "",
+            Level::Warn => "⚠ ",
+            Level::Error => "❌ ",
+        }
+    }
+}
+
+pub struct Notification
+{
+    pub level : Level,
+    pub text : String,
+    // how many times the same thing came in while it was up
+    pub count : usize,
+    expires_at : Instant,
+}
+
+impl Notification
+{
+    /**
+        What gets drawn, repeats show up as a count instead of more lines
+    **/
+    pub fn display(&self) -> String
+    {
+        if self.count > 1 {
+            format!("{}{} (x{})", self.level.icon(), self.text, self.count)
+        } else {
+            format!("{}{}", self.level.icon(), self.text)
+        }
+    }
+}
+
+pub struct Notifications
+{
+    // oldest first
+    queue : VecDeque<Notification>,
+}
+
+impl Notifications
+{
+    pub fn new() -> Self
+    {
+        Notifications { queue : VecDeque::new() }
+    }
+
+    /**
+        Adds a notification, the same one coming in again bumps its count
+        and stays up longer instead of stacking a copy
+    **/
+    pub fn push(&mut self, level : Level, text : String)
+    {
+        // longer text gets a bit more time to be read, a second per 40 characters
+        let expires_at = Instant::now() + level.duration() + Duration::from_secs(text.chars().count() as u64 / 40);
+
+        if let Some(index) = self.queue.iter().position(|n| n.level == level && n.text == text) {
+            if let Some(mut existing) = self.queue.remove(index) {
+                existing.count += 1;
+                existing.expires_at = expires_at;
+                self.queue.push_back(existing);
+            }
+            return;
+        }
+
+        self.queue.push_back(Notification { level, text, count : 1, expires_at });
+        while self.queue.len() > MAX_VISIBLE
+        {
+            self.queue.pop_front();
+        }
+    }
+
+    /**
+        Drops the ones whose time is up
+    **/
+    pub fn expire(&mut self)
+    {
+        let now = Instant::now();
+        self.queue.retain(|n| n.expires_at > now);
+    }
+
+    /**
+        When the next one runs out, the main loop wakes up then to redraw
+    **/
+    pub fn next_expiry(&self) -> Option<Instant>
+    {
+        self.queue.iter().map(|n| n.expires_at).min()
+    }
+
+    /**
+        Oldest first, so the newest ends up at the bottom of the stack
+    **/
+    pub fn visible(&self) -> impl Iterator<Item = &Notification>
+    {
+        self.queue.iter()
+    }
+
+    /**
+        The newest one, all that fits when the layout has no rows to spare
+    **/
+    pub fn latest(&self) -> Option<&Notification>
+    {
+        self.queue.back()
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+/*
+
+Where comfyfy keeps its files, follows the XDG base directory spec
+and falls back to the usual spots under $HOME when the vars are unset
+
+*/
+
+fn home_dir() -> PathBuf
+{
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn xdg_dir(var : &str, fallback : &str) -> PathBuf
+{
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir().join(fallback),
+    }
+}
+
+/**
+        ~/.cache/comfyfy, safe to delete at any time
+**/
+pub fn cache_dir() -> PathBuf
+{
+    xdg_dir("XDG_CACHE_HOME", ".cache").join("comfyfy")
+}
+
+/**
+        ~/.config/comfyfy
+**/
+pub fn config_dir() -> PathBuf
+{
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("comfyfy")
+}
+
+/**
+        ~/.local/state/comfyfy, for things worth keeping between runs
+        that arent settings, like the command history
+**/
+pub fn state_dir() -> PathBuf
+{
+    xdg_dir("XDG_STATE_HOME", ".local/state").join("comfyfy")
+}
+
+/**
+        Expands a leading ~ to the home directory, relative paths are
+        taken from base when there is one
+**/
+pub fn resolve(path : &str, base : Option<&Path>) -> PathBuf
+{
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None if path == "~" => home_dir(),
+        None => PathBuf::from(path),
+    };
+
+    match base {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    }
+}
+
+/**
+        Turns a name like "AC/DC" into something safe to use as a file name
+**/
+pub fn sanitize_file_name(name : &str) -> String
+{
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .trim_start_matches('.')
+        .to_string()
+}
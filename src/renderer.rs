@@ -0,0 +1,316 @@
+use std::io::{self, Write};
+use crossterm::{
+    cursor::{
+        Hide,
+        MoveTo,
+        Show
+    },
+    style::{
+        Attribute,
+        Color,
+        Print,
+        SetAttribute,
+        SetBackgroundColor,
+        SetForegroundColor
+    },
+    terminal::{
+        Clear,
+        ClearType
+    },
+    QueueableCommand
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::text;
+use super::theme::ColorDepth;
+
+/*
+
+Everything on screen is first drawn into a Frame of cells in memory, the
+Renderer keeps the frame that is currently on the terminal and only sends
+the cells that changed, all queued up and flushed once per render
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Style
+{
+    pub fg : Option<Color>,
+    pub bg : Option<Color>,
+    pub bold : bool,
+    // swaps fg and bg, what selections use when there are no colors
+    pub reverse : bool,
+}
+
+impl Style
+{
+    pub fn fg(color : Color) -> Self
+    {
+        Style { fg : Some(color), ..Style::default() }
+    }
+
+    pub fn bg(color : Color) -> Self
+    {
+        Style { bg : Some(color), ..Style::default() }
+    }
+
+    pub fn bold(self) -> Self
+    {
+        Style { bold : true, ..self }
+    }
+
+    pub fn reverse(self) -> Self
+    {
+        Style { reverse : true, ..self }
+    }
+
+    /**
+        This style with whatever other sets laid over it
+    **/
+    pub fn patch(self, other : Style) -> Self
+    {
+        Style {
+            fg : other.fg.or(self.fg),
+            bg : other.bg.or(self.bg),
+            bold : self.bold || other.bold,
+            reverse : self.reverse || other.reverse,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Cell
+{
+    // empty when the cell is covered by the wide character to its left
+    pub symbol : String,
+    pub style : Style,
+}
+
+impl Default for Cell
+{
+    fn default() -> Self
+    {
+        Cell { symbol : " ".to_string(), style : Style::default() }
+    }
+}
+
+/**
+        Escape sequences for the image protocols that draw over a block of
+        cells, they are sent as is after the cells and cleared with clear
+**/
+#[derive(Clone, PartialEq)]
+pub struct Graphic
+{
+    pub x : u16,
+    pub y : u16,
+    pub payload : String,
+    pub clear : &'static str,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Frame
+{
+    pub width : u16,
+    pub height : u16,
+    cells : Vec<Cell>,
+    pub graphics : Vec<Graphic>,
+    // where the terminal cursor should be left once the frame is drawn
+    pub cursor : Option<(u16, u16)>,
+}
+
+impl Frame
+{
+    pub fn new(width : u16, height : u16) -> Self
+    {
+        Frame {
+            width,
+            height,
+            cells : vec![Cell::default(); width as usize * height as usize],
+            graphics : Vec::new(),
+            cursor : None,
+        }
+    }
+
+    fn index(&self, x : u16, y : u16) -> Option<usize>
+    {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_cell(&mut self, x : u16, y : u16, symbol : &str, style : Style)
+    {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = Cell { symbol : symbol.to_string(), style };
+        }
+    }
+
+    /**
+        Writes text starting at x, y and clips it at the right edge, wide
+        characters take two cells, returns the x right after the text
+    **/
+    pub fn set_string(&mut self, x : u16, y : u16, text : &str, style : Style) -> u16
+    {
+        let mut x = x;
+
+        for grapheme in text.graphemes(true)
+        {
+            let width = text::grapheme_width(grapheme) as u16;
+            // zero width leftovers like a lone combining accent have nowhere to go
+            if width == 0 {
+                continue;
+            }
+            if x.saturating_add(width) > self.width {
+                break;
+            }
+
+            self.set_cell(x, y, grapheme, style);
+            for covered in 1..width
+            {
+                self.set_cell(x + covered, y, "", style);
+            }
+            x += width;
+        }
+        x
+    }
+}
+
+pub struct Renderer
+{
+    previous : Option<Frame>,
+    // set when the screen can no longer be trusted to match previous
+    invalid : bool,
+    // whether the terminal cursor is showing, None when we dont know
+    cursor_visible : Option<bool>,
+    // colors are brought down to what the terminal can show on the way out
+    color_depth : ColorDepth,
+}
+
+impl Renderer
+{
+    pub fn new(color_depth : ColorDepth) -> Self
+    {
+        Renderer { previous : None, invalid : false, cursor_visible : None, color_depth }
+    }
+
+    /**
+        Makes the next render redraw every cell, needed after something
+        else has written to the terminal
+    **/
+    pub fn invalidate(&mut self)
+    {
+        self.invalid = true;
+    }
+
+    /**
+        Switches the terminal over to a style, starting from a clean slate
+        since attributes cant be turned off one at a time everywhere
+    **/
+    fn queue_style(&self, style : Style, out : &mut impl Write) -> io::Result<()>
+    {
+        out.queue(SetAttribute(Attribute::Reset))?;
+        if let Some(fg) = style.fg.and_then(|c| self.color_depth.convert(c)) {
+            out.queue(SetForegroundColor(fg))?;
+        }
+        if let Some(bg) = style.bg.and_then(|c| self.color_depth.convert(c)) {
+            out.queue(SetBackgroundColor(bg))?;
+        }
+        if style.bold {
+            out.queue(SetAttribute(Attribute::Bold))?;
+        }
+        if style.reverse {
+            out.queue(SetAttribute(Attribute::Reverse))?;
+        }
+        Ok(())
+    }
+
+    /**
+        Sends the difference between the last frame and this one to out
+    **/
+    pub fn render(&mut self, frame : Frame, out : &mut impl Write) -> io::Result<()>
+    {
+        // a new size or a change in images means everything has to go out again
+        let invalid = std::mem::take(&mut self.invalid);
+        let previous = match self.previous.take() {
+            Some(p) if !invalid && p.width == frame.width && p.height == frame.height && p.graphics == frame.graphics => Some(p),
+            Some(p) => {
+                for graphic in &p.graphics
+                {
+                    out.queue(Print(graphic.clear))?;
+                }
+                None
+            }
+            None => None,
+        };
+
+        if previous.is_none()
+        {
+            out.queue(SetAttribute(Attribute::Reset))?;
+            out.queue(Clear(ClearType::All))?;
+        }
+
+        let blank = Cell::default();
+        let mut cursor : Option<(u16, u16)> = None;
+        let mut style : Option<Style> = None;
+
+        for y in 0..frame.height
+        {
+            for x in 0..frame.width
+            {
+                let i = y as usize * frame.width as usize + x as usize;
+                let cell = &frame.cells[i];
+
+                // after a clear the screen already matches any blank cell
+                let unchanged = match &previous {
+                    Some(p) => p.cells[i] == *cell,
+                    None => *cell == blank,
+                };
+                if cell.symbol.is_empty() || unchanged {
+                    continue;
+                }
+
+                if cursor != Some((x, y)) {
+                    out.queue(MoveTo(x, y))?;
+                }
+                if style != Some(cell.style) {
+                    self.queue_style(cell.style, out)?;
+                    style = Some(cell.style);
+                }
+                out.queue(Print(&cell.symbol))?;
+
+                let width = text::grapheme_width(&cell.symbol).max(1) as u16;
+                cursor = Some((x + width, y));
+            }
+        }
+        out.queue(SetAttribute(Attribute::Reset))?;
+
+        if previous.is_none()
+        {
+            for graphic in &frame.graphics
+            {
+                out.queue(MoveTo(graphic.x, graphic.y))?;
+                out.queue(Print(&graphic.payload))?;
+            }
+        }
+
+        // the cursor only shows where something is being typed
+        if let Some((x, y)) = frame.cursor {
+            out.queue(MoveTo(x, y))?;
+        }
+        let visible = frame.cursor.is_some();
+        if previous.is_none() || self.cursor_visible != Some(visible) {
+            if visible {
+                out.queue(Show)?;
+            } else {
+                out.queue(Hide)?;
+            }
+            self.cursor_visible = Some(visible);
+        }
+
+        out.flush()?;
+        self.previous = Some(frame);
+        Ok(())
+    }
+}
@@ -13,56 +13,259 @@ use std::{
     io::{Read, Write}
 };
 use reqwest::{Client, Response};
-use webbrowser;
+use tokio::sync::Mutex;
 
+use super::text;
+use super::logging::{self, debug, info, warn};
 
+
+impl SpotifyCurrentPlaying
+{
+    /**
+        All the artists joined with ", ", if that is wider than max_width
+        columns the trailing ones get dropped and counted instead like "A, B +2 more"
+    **/
+    pub fn artist_credit(&self, max_width : usize) -> String
+    {
+        let names : Vec<&str> = self.artists.iter().map(|a| a.name.as_str()).collect();
+
+        let full = names.join(", ");
+        if text::display_width(&full) <= max_width {
+            return full;
+        }
+
+        for shown in (1..names.len()).rev()
+        {
+            let credit = format!("{} +{} more", names[..shown].join(", "), names.len() - shown);
+            if text::display_width(&credit) <= max_width {
+                return credit;
+            }
+        }
+
+        // not even one name fits, the caller truncates whatever is left
+        match names.len() {
+            1 => full,
+            n => format!("{} +{} more", names[0], n - 1),
+        }
+    }
+}
+
+/**
+        One credited artist on the playing track, local files have no id or uri
+**/
+pub struct SpotifyArtistRef
+{
+    pub name : String,
+    pub id : Option<String>,
+    // playing it as a context plays the artists popular songs
+    pub uri : Option<String>,
+}
+
+#[derive(PartialEq)]
+pub enum PlayingType
+{
+    Track,
+    Episode
+}
+
+/**
+        For episodes the song is the episode name, the album is the show
+        and the artist is the publisher, the full show is kept in show
+**/
 pub struct SpotifyCurrentPlaying
 {
-    pub artists : String,
+    pub playing_type : PlayingType,
+    pub artists : Vec<SpotifyArtistRef>,
     pub album : String,
+    pub album_id : Option<String>,
     pub song : String,
+    pub show : Option<SpotifyShow>,
+    pub image_url : Option<String>,
+    pub is_playing : bool,
+    pub progress_ms : u64,
+    pub duration_ms : u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum RepeatState
+{
+    #[default]
+    Off,
+    // the whole album or playlist over again
+    Context,
+    Track
+}
+
+/**
+        Where playback is and how it is set to play, the device is None
+        when nothing is playing anywhere
+**/
+#[derive(Default)]
+pub struct SpotifyPlayerState
+{
+    pub device : Option<String>,
+    // some devices like cast speakers dont let their volume be set
+    pub volume : Option<u64>,
+    pub shuffle : bool,
+    pub repeat : RepeatState,
+}
+
+/**
+        Spotify answered with 429, retry_after is how many seconds it wants us to wait
+**/
+#[derive(Debug)]
+pub struct RateLimited
+{
+    pub retry_after : u64,
+}
+
+impl std::fmt::Display for RateLimited
+{
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        write!(f, "Rate limited by spotify, retrying in {}s", self.retry_after)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+pub struct SpotifyShow
+{
+    pub id : String,
+    pub name : String,
+    pub publisher : String,
+    pub total_episodes : u64,
+}
+
+/**
+        An episode with where the user left off in it
+**/
+pub struct SpotifyEpisode
+{
+    pub name : String,
+    pub uri : String,
+    pub release_date : String,
+    pub duration_ms : u64,
+    pub resume_position_ms : u64,
+    pub fully_played : bool,
+}
+
+/**
+        A single track as it shows up in album tracklists and artist top tracks
+**/
+pub struct SpotifyTrack
+{
+    pub name : String,
+    pub artists : String,
+    pub duration_ms : u64,
+    pub track_number : u64,
+    // local files have none and cant be queued
+    pub uri : Option<String>,
+}
+
+/**
+        Just enough of an album to list it on an artist page
+**/
+pub struct SpotifyAlbumSummary
+{
+    pub id : String,
+    pub name : String,
+    pub release_date : String,
+}
+
+/**
+        Just enough of an artist to list it under related artists
+**/
+pub struct SpotifyArtistSummary
+{
+    pub id : String,
+    pub name : String,
+}
+
+pub struct SpotifyAlbum
+{
+    pub id : String,
+    pub name : String,
+    pub artists : String,
+    pub release_date : String,
+    pub tracks : Vec<SpotifyTrack>,
+}
+
+pub struct SpotifyArtist
+{
+    pub id : String,
+    pub name : String,
+    pub genres : Vec<String>,
+    pub followers : u64,
+    pub top_tracks : Vec<SpotifyTrack>,
+    pub albums : Vec<SpotifyAlbumSummary>,
+    pub singles : Vec<SpotifyAlbumSummary>,
+    pub related : Vec<SpotifyArtistSummary>,
+}
+
+/**
+        Something spotify can play on, a phone, a speaker or this computer
+**/
+pub struct SpotifyDevice
+{
+    pub id : String,
+    pub name : String,
+}
+
+pub struct SpotifyPlaylist
+{
+    pub name : String,
+    pub uri : String,
 }
 
 /** 
-        This is the struct that will hold the token and the expiry time
+        The token and the expiry time
 **/
-pub struct SpotifyClientAuth
+struct Tokens
 {
     access_token : String,
     refresh_token : String,
     expires_at : Instant,
 }
 
+/** 
+        This is the struct that will hold the tokens, shared by every request
+**/
+pub struct SpotifyClientAuth
+{
+    // only locked to read the token or refresh it, so requests dont wait on each other
+    tokens : Mutex<Tokens>,
+}
+
 impl SpotifyClientAuth 
 {
-    pub async fn get_token(&mut self) -> Result<String, Box<dyn std::error::Error>>
+    pub async fn get_token(&self) -> Result<String, Box<dyn std::error::Error>>
     {
+        // held through a refresh so two requests dont both refresh
+        let mut tokens = self.tokens.lock().await;
         // check if the token has expired
-        if Instant::now() >= self.expires_at {
+        if Instant::now() >= tokens.expires_at {
             // refresh it 
-            let (access_token, expires_in , refresh_token) = SpotifyClientAuth::refresh_new_tokens(self.refresh_token.clone()).await?;
+            let (access_token, expires_in , refresh_token) = SpotifyClientAuth::refresh_new_tokens(tokens.refresh_token.clone()).await?;
             // update the struct
 
-            self.access_token = access_token;
-            self.expires_at = Instant::now() + Duration::from_secs(expires_in);
-            self.refresh_token = refresh_token;
+            tokens.access_token = access_token;
+            tokens.expires_at = Instant::now() + Duration::from_secs(expires_in);
+            tokens.refresh_token = refresh_token;
         }
-        // and return it
-        Ok(self.access_token.clone())
+        // and return a copy so the lock goes before the request is sent
+        Ok(tokens.access_token.clone())
     }
 
-    pub async fn add_debug_log(&mut self, log : String) -> Result<(), Box<dyn std::error::Error>>
+    fn from_tokens(access_token : String, expires_in : u64, refresh_token : String) -> Self
     {
-        // this is just a simple function to add a debug log to a file called debug.log
-        // if it doesnt exist it will create it
-        let mut file = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("debug.log")?;
-        if let Err(e) = writeln!(file, "{}", log) {
-            eprintln!("Couldn't write to file: {}", e);
+        SpotifyClientAuth {
+            tokens : Mutex::new(Tokens {
+                access_token,
+                refresh_token,
+                expires_at : Instant::now() + Duration::from_secs(expires_in)
+            })
         }
-        Ok(())
     }
 
     /** 
@@ -86,13 +289,7 @@ impl SpotifyClientAuth
                 // get the token and the expiry time
                 let (access_token, expires_in, refresh_token) = SpotifyClientAuth::refresh_new_tokens(saved_refresh_token.clone()).await?;
                 // return the struct
-                return Ok(
-                    Self {
-                        access_token,
-                        refresh_token,
-                        expires_at : Instant::now() + Duration::from_secs(expires_in)
-                    }
-                )
+                return Ok(Self::from_tokens(access_token, expires_in, refresh_token))
             }
         }
 
@@ -103,13 +300,7 @@ impl SpotifyClientAuth
         // save the refresh token to a file
         fs::write(token_path, refresh_token.clone())?;
 
-        Ok(
-            Self {
-                access_token,
-                refresh_token,
-                expires_at : Instant::now() + Duration::from_secs(expires_in)
-            }
-        )
+        Ok(Self::from_tokens(access_token, expires_in, refresh_token))
     }
 
     pub async fn refresh_new_tokens(refresh_token : String) -> Result<(String, u64, String), Box<dyn std::error::Error>>
@@ -117,6 +308,8 @@ impl SpotifyClientAuth
         // this is the same as refresh but static so that it can be called from anywhere
         let client_id = std::env::var("CLIENT_ID").expect("CLIENT_ID not found in .env file");
         let client_secret = std::env::var("CLIENT_SECRET").expect("CLIENT_SECRET not found in .env file");
+        logging::add_secret(&refresh_token);
+        logging::add_secret(&client_secret);
 
         let request = "https://accounts.spotify.com/api/token";
 
@@ -138,10 +331,12 @@ impl SpotifyClientAuth
 
         let access_token = response["access_token"].as_str().unwrap().to_string();
         let expires_in = response["expires_in"].as_u64().unwrap();
+        logging::add_secret(&access_token);
         
         let mut refresh_token : String = "".to_string();
         if response["refresh_token"].is_string() {
             refresh_token = response["refresh_token"].as_str().unwrap().to_string();
+            logging::add_secret(&refresh_token);
         }
 
         Ok((access_token, expires_in, refresh_token))
@@ -155,6 +350,7 @@ impl SpotifyClientAuth
         // get the client id and the client secret from the .env file
         let client_id = std::env::var("CLIENT_ID").expect("CLIENT_ID not found in .env file");
         let client_secret = std::env::var("CLIENT_SECRET").expect("CLIENT_SECRET not found in .env file");
+        logging::add_secret(&client_secret);
     
         // wanna post to this url
         let request = "https://accounts.spotify.com/api/token";
@@ -166,7 +362,7 @@ impl SpotifyClientAuth
         params.insert("redirect_uri", "http://localhost:8888/");
         params.insert("client_id", &client_id);
         params.insert("client_secret", &client_secret);
-        params.insert("scope", "user-read-currently-playing user-read-playback-state user-modify-playback-state");
+        params.insert("scope", "user-read-currently-playing user-read-playback-state user-modify-playback-state user-library-read user-read-playback-position playlist-read-private playlist-read-collaborative");
     
         let client = Client::new();
         let response : Value = client
@@ -178,13 +374,15 @@ impl SpotifyClientAuth
             .json()
             .await?;
 
-        for (key, value) in response.as_object().unwrap() {
-            println!("{}: {}", key, value);
-        }
-    
         let token = response["access_token"].as_str().unwrap().to_string();
         let expires_in = response["expires_in"].as_u64().unwrap();
         let refresh_token = response["refresh_token"].as_str().unwrap().to_string();
+        logging::add_secret(&token);
+        logging::add_secret(&refresh_token);
+        logging::add_secret(&client_secret);
+
+        // only once the tokens in it are known secrets
+        debug!("token response {}", response);
     
         Ok((token, expires_in, refresh_token))
     }
@@ -195,7 +393,7 @@ impl SpotifyClientAuth
     async fn open_auth_login() -> Result<String, Box<dyn std::error::Error>>
     {
         let client_id = std::env::var("CLIENT_ID").expect("CLIENT_ID not found in .env file");
-        let scopes = "user-read-currently-playing user-read-playback-state user-modify-playback-state";
+        let scopes = "user-read-currently-playing user-read-playback-state user-modify-playback-state user-library-read user-read-playback-position playlist-read-private playlist-read-collaborative";
         let redirect_uri = format!(
             "https://accounts.spotify.com/authorize?client_id={}&response_type=code&redirect_uri=http://localhost:8888/&scope={}",
             client_id, scopes
@@ -211,10 +409,10 @@ impl SpotifyClientAuth
         for stream in listener.incoming() {
             let mut stream = stream?;
             let mut buffer = [0; 1024];
-            stream.read(&mut buffer)?;
+            let read = stream.read(&mut buffer)?;
 
             // Convert bytes to string
-            let request = String::from_utf8_lossy(&buffer[..]);
+            let request = String::from_utf8_lossy(&buffer[..read]);
 
             // Extract the "code" from the URL
             if let Some(code_start) = request.find("code=") {
@@ -234,59 +432,42 @@ impl SpotifyClientAuth
         Ok("Failed to get code".to_string())
     }
 
-    pub async fn pause(&mut self) -> Result<(), Box<dyn std::error::Error>>
+    /**
+        Pauses when something is playing and resumes otherwise, true when it paused
+    **/
+    pub async fn pause(&self) -> Result<bool, Box<dyn std::error::Error>>
     {
-        let mut request = "https://api.spotify.com/v1/me/player";
-        let mut client = Client::new();
+        // now inside here we want to get the is playing value
+        let player = self.get_player().await?;
+        let is_playing = player.get("is_playing").and_then(|p| p.as_bool()).unwrap_or(false);
+
+        // then we do the pause or the play version
+        let request = if is_playing {
+            "https://api.spotify.com/v1/me/player/pause"
+        } else {
+            "https://api.spotify.com/v1/me/player/play"
+        };
+        let client = Client::new();
 
         let response = client
-            .get(request)
+            .put(request)
             .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .header("Content-Length", "0")
             .send()
             .await?;
+        let response = Self::check_response(response).await?;
 
-        // now inside here we want to get the is playing value
-        let response_json: Value = response.json().await?;
-        let is_playing = response_json.get("is_playing").unwrap().as_bool().unwrap();
-
-        if is_playing
-        {
-            request = "https://api.spotify.com/v1/me/player/pause";
-            client = Client::new();
-
-            let response = client
-                .put(request)
-                .header("Authorization", format!("Bearer {}", self.get_token().await?))
-                .header("Content-Length", "0")
-                .send()
-                .await?;
-            if response.status() == reqwest::StatusCode::NO_CONTENT {
-                self.add_debug_log("⏸ Paused".to_string()).await?;
-            }
-        } else {
-            // then we do the play version
-            request = "https://api.spotify.com/v1/me/player/play";
-            client = Client::new();
-
-            let response = client
-                .put(request)
-                .header("Authorization" , format!("Bearer {}", self.get_token().await?))
-                .header("Content-Length", "0")
-                .send()
-                .await?;
-
-            if response.status() == reqwest::StatusCode::NO_CONTENT {
-                self.add_debug_log("▶️ Resumed".to_string()).await?;
-            }
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("{}", if is_playing { "paused" } else { "resumed" });
         }
-        Ok(())
+        Ok(is_playing)
     }
 
     /**
         Helper Function to send playback info for going back and forward
         This is a post request
     **/
-    async fn send_play_back_info(&mut self,  request : &str) -> Result<Response, Box<dyn std::error::Error>>
+    async fn send_play_back_info(&self,  request : &str) -> Result<Response, Box<dyn std::error::Error>>
     {
         let client = Client::new();
 
@@ -297,34 +478,39 @@ impl SpotifyClientAuth
             .send()
             .await?;
 
-        Ok(response)
+        Self::check_response(response).await
     }
-    pub async fn skip_back(&mut self) -> Result<(), Box<dyn std::error::Error>>
+    pub async fn skip_back(&self) -> Result<(), Box<dyn std::error::Error>>
     {
         let request = "https://api.spotify.com/v1/me/player/previous";
         let response : Response = self.send_play_back_info(request).await?;
 
         if response.status() == reqwest::StatusCode::NO_CONTENT {
-            self.add_debug_log("⏮ Skipped back".to_string()).await?;
+            info!("skipped back");
         }
 
         Ok(())
     }
-    pub async fn skip_forward(&mut self) -> Result<(), Box<dyn std::error::Error>>
+    pub async fn skip_forward(&self) -> Result<(), Box<dyn std::error::Error>>
     {
         let request = "https://api.spotify.com/v1/me/player/next";
         let response : Response = self.send_play_back_info(request).await?;
 
         if response.status() == reqwest::StatusCode::NO_CONTENT {
-            self.add_debug_log("⏭ Skipped forward".to_string()).await?;
+            info!("skipped forward");
         }
 
         Ok(())
     }
-    pub async fn get_current_playing(&mut self) -> Result<Option<SpotifyCurrentPlaying>, Box<dyn std::error::Error>>
+    /**
+        The whole player rather than just what is playing, so the device
+        and the shuffle and repeat state come along with the song
+    **/
+    pub async fn get_current_playing(&self) -> Result<(SpotifyPlayerState, Option<SpotifyCurrentPlaying>), Box<dyn std::error::Error>>
     {
 
-        let request = "https://api.spotify.com/v1/me/player/currently-playing";
+        // without additional_types spotify leaves the item empty for podcasts
+        let request = "https://api.spotify.com/v1/me/player?additional_types=track,episode";
         let client = Client::new();
 
         let response = client
@@ -333,23 +519,61 @@ impl SpotifyClientAuth
             .send()
         .await?;
 
-        let token = self.get_token().await?;
-        self.add_debug_log(format!("Token: {}", token)).await?;
-
+        let response = Self::check_response(response).await?;
 
-        // Check if the response is empty (no currently playing track)
+        // Check if the response is empty (no device is playing anything)
         if response.status() == reqwest::StatusCode::NO_CONTENT {
-            return Ok(None); // No song is playing
+            return Ok((SpotifyPlayerState::default(), None));
         }
 
         let response_json: Value = response.json().await?;
+        let device = response_json.get("device");
+        let state = SpotifyPlayerState {
+            device: device.and_then(|d| d.get("name")).and_then(|n| n.as_str()).map(|n| n.to_string()),
+            volume: device.and_then(|d| d.get("volume_percent")).and_then(|v| v.as_u64()),
+            shuffle: response_json.get("shuffle_state").and_then(|s| s.as_bool()).unwrap_or(false),
+            repeat: match response_json.get("repeat_state").and_then(|r| r.as_str()) {
+                Some("context") => RepeatState::Context,
+                Some("track") => RepeatState::Track,
+                _ => RepeatState::Off,
+            },
+        };
 
+        Ok((state, SpotifyClientAuth::parse_current_playing(&response_json)))
+    }
+
+    fn parse_current_playing(response_json : &Value) -> Option<SpotifyCurrentPlaying>
+    {
         // Handle case where no song is playing
-        if response_json.get("item").is_none() {
-            return Ok(None); // Nothing is playing
-        }
+        let Some(item) = response_json.get("item").filter(|i| !i.is_null()) else {
+            return None; // Nothing is playing
+        };
 
-        let item = response_json.get("item").unwrap();
+        let is_playing = response_json.get("is_playing").and_then(|p| p.as_bool()).unwrap_or(false);
+        let progress_ms = response_json.get("progress_ms").and_then(|p| p.as_u64()).unwrap_or(0);
+        let duration_ms = item.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(0);
+
+        if response_json.get("currently_playing_type").and_then(|t| t.as_str()) == Some("episode")
+        {
+            let show = item.get("show").and_then(SpotifyClientAuth::parse_show);
+            return Some(SpotifyCurrentPlaying {
+                playing_type: PlayingType::Episode,
+                artists: vec![SpotifyArtistRef {
+                    name: show.as_ref().map(|s| s.publisher.clone()).unwrap_or("Unknown Publisher".to_string()),
+                    id: None,
+                    uri: None,
+                }],
+                album: show.as_ref().map(|s| s.name.clone()).unwrap_or("Unknown Show".to_string()),
+                album_id: None,
+                song: item.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Episode").to_string(),
+                image_url: item.get("images").and_then(SpotifyClientAuth::pick_image)
+                    .or_else(|| item.get("show").and_then(|s| s.get("images")).and_then(SpotifyClientAuth::pick_image)),
+                show,
+                is_playing,
+                progress_ms,
+                duration_ms,
+            });
+        }
 
         // Safely extract song name
         let song = item.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Song");
@@ -360,18 +584,539 @@ impl SpotifyClientAuth
             .and_then(|s| s.as_str())
             .unwrap_or("Unknown Album");
 
-        // Extract first artist's name safely
-        let artist = item.get("artists")
-            .and_then(|a| a.as_array())
-            .and_then(|arr| arr.get(0))
-            .and_then(|a| a.get("name"))
+        let album_id = item.get("album")
+            .and_then(|a| a.get("id"))
             .and_then(|s| s.as_str())
-            .unwrap_or("Unknown Artist");
+            .map(|s| s.to_string());
 
-        Ok(Some(SpotifyCurrentPlaying {
-            artists: artist.to_string(),
+        // Extract every credited artist safely
+        let mut artists : Vec<SpotifyArtistRef> = item.get("artists")
+            .and_then(|a| a.as_array())
+            .map(|arr| arr.iter().map(|a| SpotifyArtistRef {
+                name: a.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Artist").to_string(),
+                id: a.get("id").and_then(|s| s.as_str()).map(|s| s.to_string()),
+                uri: a.get("uri").and_then(|s| s.as_str()).map(|s| s.to_string()),
+            }).collect())
+            .unwrap_or_default();
+
+        if artists.is_empty() {
+            artists.push(SpotifyArtistRef { name: "Unknown Artist".to_string(), id: None, uri: None });
+        }
+
+        Some(SpotifyCurrentPlaying {
+            playing_type: PlayingType::Track,
+            artists,
             album: album.to_string(),
+            album_id,
             song: song.to_string(),
-        }))
+            show: None,
+            image_url: item.get("album").and_then(|a| a.get("images")).and_then(SpotifyClientAuth::pick_image),
+            is_playing,
+            progress_ms,
+            duration_ms,
+        })
+    }
+
+    /**
+        Helper Function for the plain GET endpoints that just hand back json
+    **/
+    async fn get_json(&self, request : &str) -> Result<Value, Box<dyn std::error::Error>>
+    {
+        let client = Client::new();
+
+        let response = client
+            .get(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .send()
+            .await?;
+
+        let response = Self::check_response(response).await?;
+
+        Ok(response.json().await?)
+    }
+
+    /**
+        The state of the player, spotify answers with nothing at all when
+        there is no device to play on
+    **/
+    async fn get_player(&self) -> Result<Value, Box<dyn std::error::Error>>
+    {
+        let client = Client::new();
+
+        let response = client
+            .get("https://api.spotify.com/v1/me/player?additional_types=track,episode")
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            return Err("No active device".into());
+        }
+        Ok(response.json().await?)
+    }
+
+    /**
+        Passes a successful response through and turns a failed one into an
+        error worth showing, spotify puts a reason in the body for the
+        player endpoints like when nothing is playing anywhere
+    **/
+    async fn check_response(response : Response) -> Result<Response, Box<dyn std::error::Error>>
+    {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response.headers()
+                .get("Retry-After")
+                .and_then(|r| r.to_str().ok())
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(1);
+            warn!("rate limited for {}s", retry_after);
+            return Err(Box::new(RateLimited { retry_after }));
+        }
+
+        let body : Value = response.json().await.unwrap_or(Value::Null);
+        let reason = body.pointer("/error/reason").and_then(|r| r.as_str()).unwrap_or_default();
+        let message = match reason {
+            "NO_ACTIVE_DEVICE" => "No active device".to_string(),
+            "PREMIUM_REQUIRED" => "Spotify Premium is needed to control playback".to_string(),
+            _ => match body.pointer("/error/message").and_then(|m| m.as_str()) {
+                Some(message) => format!("{} ({})", message, status),
+                None => status.to_string(),
+            },
+        };
+        warn!("spotify answered {}: {}", status, body);
+        Err(message.into())
+    }
+
+    /**
+        Joins every artist name in a json "artists" array with ", "
+    **/
+    fn artist_names(value : &Value) -> String
+    {
+        let names : Vec<&str> = value.get("artists")
+            .and_then(|a| a.as_array())
+            .map(|arr| arr.iter().filter_map(|a| a.get("name").and_then(|s| s.as_str())).collect())
+            .unwrap_or_default();
+
+        if names.is_empty() {
+            "Unknown Artist".to_string()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    /**
+        Spotify lists covers largest first, we want the smallest one that is
+        still at least 300px so the download stays small but sharp enough
+    **/
+    fn pick_image(images : &Value) -> Option<String>
+    {
+        let images = images.as_array()?;
+        images.iter()
+            .rev()
+            .find(|i| i.get("width").and_then(|w| w.as_u64()).unwrap_or(0) >= 300)
+            .or_else(|| images.first())
+            .and_then(|i| i.get("url"))
+            .and_then(|u| u.as_str())
+            .map(|u| u.to_string())
+    }
+
+    fn parse_track(value : &Value) -> SpotifyTrack
+    {
+        SpotifyTrack {
+            name: value.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Song").to_string(),
+            artists: SpotifyClientAuth::artist_names(value),
+            duration_ms: value.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(0),
+            track_number: value.get("track_number").and_then(|n| n.as_u64()).unwrap_or(0),
+            uri: value.get("uri").and_then(|s| s.as_str()).filter(|u| !u.starts_with("spotify:local:")).map(|s| s.to_string()),
+        }
+    }
+
+    fn parse_album_summary(value : &Value) -> Option<SpotifyAlbumSummary>
+    {
+        Some(SpotifyAlbumSummary {
+            id: value.get("id")?.as_str()?.to_string(),
+            name: value.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Album").to_string(),
+            release_date: value.get("release_date").and_then(|s| s.as_str()).unwrap_or("").to_string(),
+        })
+    }
+
+    /**
+        Gets an album with its full tracklist, spotify pages the tracks so we
+        keep following "next" until there is nothing left
+    **/
+    pub async fn get_album(&self, album_id : &str) -> Result<SpotifyAlbum, Box<dyn std::error::Error>>
+    {
+        let request = format!("https://api.spotify.com/v1/albums/{}", album_id);
+        let album = self.get_json(&request).await?;
+
+        let mut tracks : Vec<SpotifyTrack> = Vec::new();
+        let mut page = album.get("tracks").cloned().unwrap_or(Value::Null);
+        loop
+        {
+            if let Some(items) = page.get("items").and_then(|i| i.as_array()) {
+                tracks.extend(items.iter().map(SpotifyClientAuth::parse_track));
+            }
+
+            match page.get("next").and_then(|n| n.as_str()) {
+                Some(next) => {
+                    let next = next.to_string();
+                    page = self.get_json(&next).await?;
+                }
+                None => break,
+            }
+        }
+
+        Ok(SpotifyAlbum {
+            id: album_id.to_string(),
+            name: album.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Album").to_string(),
+            artists: SpotifyClientAuth::artist_names(&album),
+            release_date: album.get("release_date").and_then(|s| s.as_str()).unwrap_or("").to_string(),
+            tracks,
+        })
+    }
+
+    /**
+        Gets an artist along with their top tracks, albums, singles and related artists
+    **/
+    pub async fn get_artist(&self, artist_id : &str) -> Result<SpotifyArtist, Box<dyn std::error::Error>>
+    {
+        let artist = self.get_json(&format!("https://api.spotify.com/v1/artists/{}", artist_id)).await?;
+
+        let top_tracks = self.get_json(&format!("https://api.spotify.com/v1/artists/{}/top-tracks?market=from_token", artist_id)).await?;
+        let top_tracks : Vec<SpotifyTrack> = top_tracks.get("tracks")
+            .and_then(|t| t.as_array())
+            .map(|arr| arr.iter().map(SpotifyClientAuth::parse_track).collect())
+            .unwrap_or_default();
+
+        let mut albums : Vec<SpotifyAlbumSummary> = Vec::new();
+        let mut singles : Vec<SpotifyAlbumSummary> = Vec::new();
+        let releases = self.get_json(&format!("https://api.spotify.com/v1/artists/{}/albums?include_groups=album,single&limit=50", artist_id)).await?;
+        if let Some(items) = releases.get("items").and_then(|i| i.as_array())
+        {
+            for item in items
+            {
+                let Some(summary) = SpotifyClientAuth::parse_album_summary(item) else { continue };
+                match item.get("album_type").and_then(|s| s.as_str()) {
+                    Some("single") => singles.push(summary),
+                    _ => albums.push(summary),
+                }
+            }
+        }
+
+        // related artists is not available for every app so an error here just means an empty list
+        // the error is only kept as text so this future can run on a background task
+        let related = self.get_json(&format!("https://api.spotify.com/v1/artists/{}/related-artists", artist_id)).await.map_err(|e| e.to_string());
+        let related : Vec<SpotifyArtistSummary> = match related {
+            Ok(related) => related.get("artists")
+                .and_then(|a| a.as_array())
+                .map(|arr| arr.iter().filter_map(|a| Some(SpotifyArtistSummary {
+                    id: a.get("id")?.as_str()?.to_string(),
+                    name: a.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Artist").to_string(),
+                })).collect())
+                .unwrap_or_default(),
+            Err(e) => {
+                warn!("related artists unavailable: {}", e);
+                Vec::new()
+            }
+        };
+
+        Ok(SpotifyArtist {
+            id: artist_id.to_string(),
+            name: artist.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Artist").to_string(),
+            genres: artist.get("genres")
+                .and_then(|g| g.as_array())
+                .map(|arr| arr.iter().filter_map(|g| g.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default(),
+            followers: artist.get("followers").and_then(|f| f.get("total")).and_then(|t| t.as_u64()).unwrap_or(0),
+            top_tracks,
+            albums,
+            singles,
+            related,
+        })
+    }
+
+    /**
+        Moves the playback position by offset_ms from wherever it is right now,
+        used for the 15 and 30 second skips on episodes
+    **/
+    pub async fn seek_relative(&self, offset_ms : i64) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let player = self.get_player().await?;
+        let progress_ms = player.get("progress_ms").and_then(|p| p.as_u64()).unwrap_or(0);
+        let duration_ms = player.get("item")
+            .and_then(|i| i.get("duration_ms"))
+            .and_then(|d| d.as_u64());
+
+        // without a duration there is nothing to stop at past the end, spotify skips to the next one then
+        let position_ms = match duration_ms {
+            Some(duration_ms) => (progress_ms as i64 + offset_ms).clamp(0, duration_ms as i64),
+            None => (progress_ms as i64 + offset_ms).max(0),
+        } as u64;
+
+        let request = format!("https://api.spotify.com/v1/me/player/seek?position_ms={}", position_ms);
+        let client = Client::new();
+        let response = client
+            .put(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .header("Content-Length", "0")
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("seeked to {}ms", position_ms);
+        }
+        Ok(())
+    }
+
+    /**
+        Turns the volume of the active device up or down by delta percent,
+        returns the new volume
+    **/
+    pub async fn change_volume(&self, delta : i64) -> Result<u64, Box<dyn std::error::Error>>
+    {
+        let player = self.get_player().await?;
+        let volume = player.get("device")
+            .and_then(|d| d.get("volume_percent"))
+            .and_then(|v| v.as_i64())
+            .ok_or("the active device has no volume control")?;
+
+        let volume = (volume + delta).clamp(0, 100) as u64;
+        self.set_volume(volume).await?;
+        Ok(volume)
+    }
+
+    /**
+        Sets the volume of the active device to a percent
+    **/
+    pub async fn set_volume(&self, volume : u64) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let volume = volume.min(100);
+        let request = format!("https://api.spotify.com/v1/me/player/volume?volume_percent={}", volume);
+        let client = Client::new();
+        let response = client
+            .put(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .header("Content-Length", "0")
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("volume {}%", volume);
+        }
+        Ok(())
+    }
+
+    /**
+        Starts playing a single uri, position_ms lets episodes pick up where they were left
+    **/
+    pub async fn play_uri(&self, uri : &str, position_ms : u64) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let request = "https://api.spotify.com/v1/me/player/play";
+        let client = Client::new();
+        let response = client
+            .put(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .json(&serde_json::json!({ "uris": [uri], "position_ms": position_ms }))
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("playing {}", uri);
+        }
+        Ok(())
+    }
+
+    fn parse_show(value : &Value) -> Option<SpotifyShow>
+    {
+        Some(SpotifyShow {
+            id: value.get("id")?.as_str()?.to_string(),
+            name: value.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Show").to_string(),
+            publisher: value.get("publisher").and_then(|s| s.as_str()).unwrap_or("Unknown Publisher").to_string(),
+            total_episodes: value.get("total_episodes").and_then(|t| t.as_u64()).unwrap_or(0),
+        })
+    }
+
+    /**
+        Gets every show the user has saved in their library
+    **/
+    pub async fn get_saved_shows(&self) -> Result<Vec<SpotifyShow>, Box<dyn std::error::Error>>
+    {
+        let mut shows : Vec<SpotifyShow> = Vec::new();
+        let mut request = "https://api.spotify.com/v1/me/shows?limit=50".to_string();
+        loop
+        {
+            let page = self.get_json(&request).await?;
+            if let Some(items) = page.get("items").and_then(|i| i.as_array()) {
+                shows.extend(items.iter().filter_map(|i| i.get("show").and_then(SpotifyClientAuth::parse_show)));
+            }
+
+            match page.get("next").and_then(|n| n.as_str()) {
+                Some(next) => request = next.to_string(),
+                None => break,
+            }
+        }
+        Ok(shows)
+    }
+
+    /**
+        Gets a show and its latest episodes, the resume points only come
+        back when the token has the user-read-playback-position scope
+    **/
+    pub async fn get_show(&self, show_id : &str) -> Result<(SpotifyShow, Vec<SpotifyEpisode>), Box<dyn std::error::Error>>
+    {
+        let show_json = self.get_json(&format!("https://api.spotify.com/v1/shows/{}", show_id)).await?;
+        let show = SpotifyClientAuth::parse_show(&show_json).ok_or("Show is missing an id")?;
+
+        let episodes_json = self.get_json(&format!("https://api.spotify.com/v1/shows/{}/episodes?limit=50&market=from_token", show_id)).await?;
+        let episodes : Vec<SpotifyEpisode> = episodes_json.get("items")
+            .and_then(|i| i.as_array())
+            .map(|arr| arr.iter().filter_map(|e| {
+                let resume_point = e.get("resume_point");
+                Some(SpotifyEpisode {
+                    name: e.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Episode").to_string(),
+                    uri: e.get("uri")?.as_str()?.to_string(),
+                    release_date: e.get("release_date").and_then(|s| s.as_str()).unwrap_or("").to_string(),
+                    duration_ms: e.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(0),
+                    resume_position_ms: resume_point.and_then(|r| r.get("resume_position_ms")).and_then(|p| p.as_u64()).unwrap_or(0),
+                    fully_played: resume_point.and_then(|r| r.get("fully_played")).and_then(|f| f.as_bool()).unwrap_or(false),
+                })
+            }).collect())
+            .unwrap_or_default();
+
+        Ok((show, episodes))
+    }
+
+    /**
+        The tracks coming up next in the player
+    **/
+    pub async fn get_queue(&self) -> Result<Vec<SpotifyTrack>, Box<dyn std::error::Error>>
+    {
+        let queue = self.get_json("https://api.spotify.com/v1/me/player/queue").await?;
+        Ok(queue.get("queue")
+            .and_then(|q| q.as_array())
+            .map(|arr| arr.iter().map(SpotifyClientAuth::parse_track).collect())
+            .unwrap_or_default())
+    }
+
+    /**
+        Adds a track or episode to the end of the queue
+    **/
+    pub async fn add_to_queue(&self, uri : &str) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let request = "https://api.spotify.com/v1/me/player/queue";
+        let client = Client::new();
+        let response = client
+            .post(request)
+            .query(&[("uri", uri)])
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .header("Content-Length", "0")
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status().is_success() {
+            info!("queued {}", uri);
+        }
+        Ok(())
+    }
+
+    /**
+        The name of the signed in account, shown in the status bar
+    **/
+    pub async fn get_profile(&self) -> Result<String, Box<dyn std::error::Error>>
+    {
+        let profile = self.get_json("https://api.spotify.com/v1/me").await?;
+        profile.get("display_name")
+            .and_then(|n| n.as_str())
+            .or_else(|| profile.get("id").and_then(|i| i.as_str()))
+            .map(|n| n.to_string())
+            .ok_or_else(|| "the profile has no name".into())
+    }
+
+    pub async fn get_devices(&self) -> Result<Vec<SpotifyDevice>, Box<dyn std::error::Error>>
+    {
+        let devices = self.get_json("https://api.spotify.com/v1/me/player/devices").await?;
+        Ok(devices.get("devices")
+            .and_then(|d| d.as_array())
+            .map(|arr| arr.iter().filter_map(|d| Some(SpotifyDevice {
+                // restricted devices come back without an id and cant be controlled
+                id: d.get("id")?.as_str()?.to_string(),
+                name: d.get("name").and_then(|s| s.as_str()).unwrap_or("Unknown Device").to_string(),
+            })).collect())
+            .unwrap_or_default())
+    }
+
+    /**
+        Moves playback over to another device and keeps it playing there
+    **/
+    pub async fn transfer_playback(&self, device_id : &str) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let request = "https://api.spotify.com/v1/me/player";
+        let client = Client::new();
+        let response = client
+            .put(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .json(&serde_json::json!({ "device_ids": [device_id], "play": true }))
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("playing on {}", device_id);
+        }
+        Ok(())
+    }
+
+    /**
+        Every playlist the user made or follows
+    **/
+    pub async fn get_playlists(&self) -> Result<Vec<SpotifyPlaylist>, Box<dyn std::error::Error>>
+    {
+        let mut playlists : Vec<SpotifyPlaylist> = Vec::new();
+        let mut request = "https://api.spotify.com/v1/me/playlists?limit=50".to_string();
+        loop
+        {
+            let page = self.get_json(&request).await?;
+            if let Some(items) = page.get("items").and_then(|i| i.as_array()) {
+                playlists.extend(items.iter().filter_map(|p| Some(SpotifyPlaylist {
+                    name: p.get("name")?.as_str()?.to_string(),
+                    uri: p.get("uri")?.as_str()?.to_string(),
+                })));
+            }
+
+            match page.get("next").and_then(|n| n.as_str()) {
+                Some(next) => request = next.to_string(),
+                None => break,
+            }
+        }
+        Ok(playlists)
+    }
+
+    /**
+        Starts playing a playlist, album, show or artist from the top
+    **/
+    pub async fn play_context(&self, context_uri : &str) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let request = "https://api.spotify.com/v1/me/player/play";
+        let client = Client::new();
+        let response = client
+            .put(request)
+            .header("Authorization", format!("Bearer {}", self.get_token().await?))
+            .json(&serde_json::json!({ "context_uri": context_uri }))
+            .send()
+            .await?;
+        let response = Self::check_response(response).await?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            info!("playing {}", context_uri);
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,124 @@
+use std::{
+    error::Error,
+    time::{Duration, Instant}
+};
+use serde::Deserialize;
+
+use super::local_time::LocalTime;
+use super::spotify_client_auth::RateLimited;
+
+/*
+
+The bar along the bottom, split into segments that each show one thing,
+which ones show up and on which side comes from the [status] config
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Segment
+{
+    Mode,
+    // the device spotify is playing on
+    Device,
+    Volume,
+    Shuffle,
+    Repeat,
+    // whether spotify can be reached and if it asked us to slow down
+    Network,
+    // the signed in account
+    Profile,
+    Clock
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StatusBarConfig
+{
+    left : Option<Vec<Segment>>,
+    right : Option<Vec<Segment>>,
+}
+
+pub struct StatusBar
+{
+    pub left : Vec<Segment>,
+    pub right : Vec<Segment>,
+}
+
+impl Default for StatusBar
+{
+    fn default() -> Self
+    {
+        StatusBar {
+            left : vec![Segment::Mode, Segment::Device, Segment::Volume],
+            right : vec![Segment::Shuffle, Segment::Repeat, Segment::Network, Segment::Profile, Segment::Clock],
+        }
+    }
+}
+
+impl StatusBar
+{
+    pub fn has(&self, segment : Segment) -> bool
+    {
+        self.left.contains(&segment) || self.right.contains(&segment)
+    }
+}
+
+impl StatusBarConfig
+{
+    /**
+        A side left out keeps its default, an empty list turns it off
+    **/
+    pub fn to_status_bar(&self) -> StatusBar
+    {
+        let default = StatusBar::default();
+        StatusBar {
+            left : self.left.clone().unwrap_or(default.left),
+            right : self.right.clone().unwrap_or(default.right),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Connection
+{
+    Online,
+    Offline,
+    // spotify sent a 429, polling waits until then
+    RateLimited(Instant)
+}
+
+impl Connection
+{
+    /**
+        What a failed request says about the connection, an error that
+        spotify answered with still means it can be reached
+    **/
+    pub fn from_error(error : &(dyn Error + 'static)) -> Self
+    {
+        if let Some(limited) = error.downcast_ref::<RateLimited>() {
+            return Connection::RateLimited(Instant::now() + Duration::from_secs(limited.retry_after));
+        }
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_connect() || e.is_timeout() => Connection::Offline,
+            _ => Connection::Online,
+        }
+    }
+
+    /**
+        True while spotify still wants us to wait
+    **/
+    pub fn is_limited(&self) -> bool
+    {
+        matches!(self, Connection::RateLimited(until) if *until > Instant::now())
+    }
+}
+
+/**
+        The local time as HH:MM
+**/
+pub fn clock() -> String
+{
+    let now = LocalTime::now();
+    format!("{:02}:{:02}", now.hour, now.minute)
+}
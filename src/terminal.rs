@@ -0,0 +1,155 @@
+use std::{
+    io::{self, stdout},
+    panic,
+    process
+};
+use crossterm::{
+    cursor::{
+        EnableBlinking,
+        Hide,
+        Show
+    },
+    event::{
+        DisableMouseCapture,
+        EnableMouseCapture
+    },
+    style::ResetColor,
+    terminal::{
+        disable_raw_mode,
+        enable_raw_mode,
+        EnterAlternateScreen,
+        LeaveAlternateScreen
+    },
+    ExecutableCommand
+};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/*
+
+Puts the terminal into the state the tui needs and makes sure it gets put
+back however we leave, a normal quit, an error bubbling up, a panic or a signal
+
+*/
+
+/**
+        Sets the terminal up for drawing, the tui gets its own screen so
+        whatever was in the shell is still there when we leave
+**/
+pub fn setup() -> io::Result<()>
+{
+    enable_raw_mode()?;
+    stdout().execute(EnterAlternateScreen)?;
+    // the renderer shows it again when something is being typed
+    stdout().execute(Hide)?;
+    stdout().execute(EnableBlinking)?;
+    stdout().execute(EnableMouseCapture)?;
+    Ok(())
+}
+
+/**
+        Hands the terminal back the way the shell expects it, errors are
+        ignored since this also runs from the panic hook and drop
+**/
+pub fn restore()
+{
+    let mut out = stdout();
+    let _ = out.execute(ResetColor);
+    let _ = out.execute(DisableMouseCapture);
+    let _ = out.execute(Show);
+    let _ = out.execute(LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+}
+
+/**
+        Ctrl-Z, gives the terminal back to the shell and stops until fg
+        brings us back, the SIGCONT that comes with fg sets it up again
+**/
+pub fn suspend()
+{
+    restore();
+    // raw mode means the terminal never sends SIGTSTP for us, and SIGSTOP
+    // cant be caught so it stops us even with the SIGTSTP handler in place
+    unsafe {
+        libc::raise(libc::SIGSTOP);
+    }
+}
+
+/**
+        Holds the terminal in tui mode for as long as it lives, dropping it
+        restores the terminal so an early return with ? cleans up too
+**/
+pub struct TerminalGuard;
+
+impl TerminalGuard
+{
+    pub fn enter() -> io::Result<Self>
+    {
+        // the default hook prints the message, restoring first means it shows up readable,
+        // a panic in a spawned task would otherwise leave the tui running in a cooked terminal
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous_hook(info);
+            process::exit(101);
+        }));
+
+        setup()?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard
+{
+    fn drop(&mut self)
+    {
+        restore();
+    }
+}
+
+pub enum TerminalSignal
+{
+    // SIGINT, SIGTERM or SIGHUP, time to clean up and go
+    Quit,
+    // SIGTSTP sent from outside, like kill -TSTP
+    Suspend,
+    // SIGCONT, whatever stopped us may have messed with the terminal
+    Continue
+}
+
+/**
+        The signals the tui cares about, all read from the main loop
+**/
+pub struct Signals
+{
+    interrupt : Signal,
+    terminate : Signal,
+    hangup : Signal,
+    suspend : Signal,
+    resume : Signal,
+}
+
+impl Signals
+{
+    pub fn new() -> io::Result<Self>
+    {
+        Ok(Signals {
+            interrupt : signal(SignalKind::interrupt())?,
+            terminate : signal(SignalKind::terminate())?,
+            hangup : signal(SignalKind::hangup())?,
+            suspend : signal(SignalKind::from_raw(libc::SIGTSTP))?,
+            resume : signal(SignalKind::from_raw(libc::SIGCONT))?,
+        })
+    }
+
+    pub async fn recv(&mut self) -> TerminalSignal
+    {
+        tokio::select!
+        {
+            _ = self.interrupt.recv() => TerminalSignal::Quit,
+            _ = self.terminate.recv() => TerminalSignal::Quit,
+            _ = self.hangup.recv() => TerminalSignal::Quit,
+            _ = self.suspend.recv() => TerminalSignal::Suspend,
+            _ = self.resume.recv() => TerminalSignal::Continue,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/*
+
+Measuring and cutting text by how many terminal columns it takes up
+rather than by bytes or chars, so CJK, emoji and accented titles line up
+and never get cut in the middle of a character
+
+*/
+
+const ELLIPSIS : &str = "...";
+// the space between the end of a scrolling title and its start coming round again
+const MARQUEE_GAP : &str = "   ";
+
+/**
+        How many columns the text takes up on screen
+**/
+pub fn display_width(text : &str) -> usize
+{
+    text.graphemes(true).map(grapheme_width).sum()
+}
+
+/**
+        Width of one user visible character, emoji sequences joined with
+        zero width joiners still only take up the one wide cell
+**/
+pub fn grapheme_width(grapheme : &str) -> usize
+{
+    let width = grapheme.width();
+    if grapheme.chars().count() > 1 {
+        width.min(2)
+    } else {
+        width
+    }
+}
+
+/**
+        The longest start of the text that fits in max_width columns
+**/
+pub fn take_width(text : &str, max_width : usize) -> &str
+{
+    let mut used = 0;
+    for (i, grapheme) in text.grapheme_indices(true)
+    {
+        used += grapheme_width(grapheme);
+        if used > max_width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/**
+        Cuts the text down to max_width columns with "..." on the end when
+        it doesnt fit, too narrow for the ellipsis just gets a plain cut
+**/
+pub fn truncate(text : &str, max_width : usize) -> String
+{
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+
+    let ellipsis_width = display_width(ELLIPSIS);
+    if max_width <= ellipsis_width {
+        return take_width(text, max_width).to_string();
+    }
+
+    format!("{}{}", take_width(text, max_width - ellipsis_width).trim_end(), ELLIPSIS)
+}
+
+/**
+        Column to start at so the text sits in the middle of width
+**/
+pub fn center_offset(text : &str, width : usize) -> usize
+{
+    width.saturating_sub(display_width(text)) / 2
+}
+
+/**
+        A max_width window into the text that has been scrolled step
+        characters along, wrapping round with a gap, text that fits is left alone
+**/
+pub fn marquee(text : &str, max_width : usize, step : usize) -> String
+{
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+
+    let looped : Vec<&str> = text.graphemes(true).chain(MARQUEE_GAP.graphemes(true)).collect();
+    let start = step % looped.len();
+
+    let mut window = String::new();
+    let mut used = 0;
+    for grapheme in looped.iter().cycle().skip(start).take(looped.len())
+    {
+        let width = grapheme_width(grapheme);
+        if used + width > max_width {
+            break;
+        }
+        window.push_str(grapheme);
+        used += width;
+    }
+    window
+}
+
+/**
+        Breaks text into lines of at most max_width columns, at spaces when
+        it can and in the middle of words that are too long for a line
+**/
+pub fn wrap(text : &str, max_width : usize) -> Vec<String>
+{
+    let mut lines : Vec<String> = Vec::new();
+    if max_width == 0 {
+        return lines;
+    }
+
+    for paragraph in text.lines()
+    {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace()
+        {
+            let mut word = word;
+            let needed = if line.is_empty() { display_width(word) } else { display_width(&line) + 1 + display_width(word) };
+            if needed <= max_width {
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // a word wider than the whole line gets chopped up
+            while display_width(word) > max_width
+            {
+                let piece = take_width(word, max_width);
+                if piece.is_empty() {
+                    break;
+                }
+                lines.push(piece.to_string());
+                word = &word[piece.len()..];
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // man, woman, girl joined with zero width joiners, one grapheme two columns wide
+    const FAMILY : &str = "👨\u{200d}👩\u{200d}👧";
+
+    #[test]
+    fn a_wide_character_at_the_cut_is_left_out()
+    {
+        assert_eq!(take_width("ab日本", 3), "ab");
+        assert_eq!(truncate("日本語です", 5), "日...");
+        assert_eq!(display_width(&truncate("日本語です", 6)), 5);
+    }
+
+    #[test]
+    fn too_narrow_for_the_ellipsis_gets_a_plain_cut()
+    {
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("hello", 1), "h");
+        assert_eq!(truncate("hello", 2), "he");
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("日本", 1), "");
+        assert_eq!(truncate("hello", 4), "h...");
+    }
+
+    #[test]
+    fn text_that_fits_is_left_alone()
+    {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(marquee("hello", 5, 3), "hello");
+    }
+
+    #[test]
+    fn a_zwj_emoji_is_one_wide_character()
+    {
+        assert_eq!(display_width(FAMILY), 2);
+        assert_eq!(take_width(FAMILY, 1), "");
+        assert_eq!(take_width(&format!("a{}b", FAMILY), 3), format!("a{}", FAMILY));
+        assert_eq!(truncate(&format!("{}{}{}", FAMILY, FAMILY, FAMILY), 5), format!("{}...", FAMILY));
+    }
+
+    #[test]
+    fn marquee_scrolls_round_with_a_gap()
+    {
+        assert_eq!(marquee("abcdef", 4, 0), "abcd");
+        assert_eq!(marquee("abcdef", 4, 2), "cdef");
+        assert_eq!(marquee("abcdef", 4, 5), "f   ");
+        assert_eq!(marquee("abcdef", 4, 9), "abcd");
+    }
+
+    #[test]
+    fn marquee_never_splits_a_wide_character()
+    {
+        assert_eq!(marquee("日本語", 3, 0), "日");
+        assert_eq!(marquee("日本語", 0, 1), "");
+        assert_eq!(marquee(&format!("{}xyz", FAMILY), 2, 0), FAMILY);
+    }
+}
@@ -0,0 +1,437 @@
+use crossterm::style::Color;
+use serde::Deserialize;
+
+use super::renderer::Style;
+
+/*
+
+The colors everything is drawn in, a theme is a handful of named styles
+that start from a preset and can be changed one by one from the [theme]
+section of the config, colors get brought down to whatever the terminal
+can show and NO_COLOR leaves only bold and reverse
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset
+{
+    #[default]
+    Dark,
+    Light,
+    HighContrast
+}
+
+const PRESETS : &[(&str, Preset)] = &[
+    ("dark", Preset::Dark),
+    ("light", Preset::Light),
+    ("high-contrast", Preset::HighContrast),
+];
+
+impl Preset
+{
+    pub fn from_name(name : &str) -> Option<Self>
+    {
+        PRESETS.iter().find(|(n, _)| *n == name).map(|(_, preset)| *preset)
+    }
+}
+
+/**
+        Every preset name, for completing :theme
+**/
+pub fn preset_names() -> impl Iterator<Item = &'static str>
+{
+    PRESETS.iter().map(|(name, _)| *name)
+}
+
+/**
+        How many colors the terminal can show
+**/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorDepth
+{
+    TrueColor,
+    Ansi256,
+    // the 16 colors every terminal has
+    Basic,
+    // NO_COLOR, only bold and reverse get through
+    Monochrome
+}
+
+impl ColorDepth
+{
+    /**
+        Guesses from the environment, NO_COLOR wins over everything else
+    **/
+    pub fn detect() -> Self
+    {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorDepth::Monochrome;
+        }
+
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term == "dumb" {
+            return ColorDepth::Monochrome;
+        }
+        if term.contains("256color") {
+            return ColorDepth::Ansi256;
+        }
+        ColorDepth::Basic
+    }
+
+    /**
+        The closest color this depth can show, None when it shows no color at all
+    **/
+    pub fn convert(&self, color : Color) -> Option<Color>
+    {
+        match (self, color)
+        {
+            (ColorDepth::Monochrome, _) => None,
+            (ColorDepth::TrueColor, color) => Some(color),
+            (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => Some(Color::AnsiValue(rgb_to_ansi256(r, g, b))),
+            (ColorDepth::Basic, Color::Rgb { r, g, b }) => Some(nearest_basic(r, g, b)),
+            (ColorDepth::Basic, Color::AnsiValue(n)) if n >= 16 => {
+                let (r, g, b) = ansi256_to_rgb(n);
+                Some(nearest_basic(r, g, b))
+            }
+            (_, color) => Some(color),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ColorSupport
+{
+    Auto,
+    Truecolor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Basic,
+    None
+}
+
+#[derive(Clone, Copy)]
+pub struct Theme
+{
+    // boxes around the now playing song, the controls and overlays
+    pub border : Style,
+    // the focused control, the selected row and the current lyric
+    pub selected : Style,
+    pub title : Style,
+    pub artist : Style,
+    // the mode line at the bottom and the notifications by level
+    pub status : Style,
+    pub warning : Style,
+    pub error : Style,
+    // the part of the song that has played
+    pub progress : Style,
+    // rows picked in visual mode
+    pub visual : Style,
+}
+
+// the names styles go by in the [theme] config
+const STYLE_NAMES : &[&str] = &["border", "selected", "title", "artist", "status", "warning", "error", "progress", "visual"];
+
+impl Theme
+{
+    pub fn preset(preset : Preset) -> Self
+    {
+        match preset
+        {
+            Preset::Dark => Theme {
+                border : Style::default(),
+                selected : Style::fg(Color::Green),
+                title : Style::default().bold(),
+                artist : Style::fg(Color::Cyan),
+                status : Style::default(),
+                warning : Style::fg(Color::Yellow),
+                error : Style::fg(Color::Red),
+                progress : Style::fg(Color::Green),
+                visual : Style::bg(Color::DarkGrey),
+            },
+            Preset::Light => Theme {
+                border : Style::fg(Color::DarkGrey),
+                selected : Style::fg(Color::DarkBlue).bold(),
+                title : Style::default().bold(),
+                artist : Style::fg(Color::DarkMagenta),
+                status : Style::fg(Color::DarkGrey),
+                warning : Style::fg(Color::DarkYellow),
+                error : Style::fg(Color::DarkRed),
+                progress : Style::fg(Color::DarkBlue),
+                visual : Style::bg(Color::Grey),
+            },
+            Preset::HighContrast => Theme {
+                border : Style::fg(Color::White).bold(),
+                selected : Style { fg : Some(Color::Black), bg : Some(Color::Yellow), bold : true, reverse : false },
+                title : Style::fg(Color::White).bold(),
+                artist : Style::fg(Color::Yellow).bold(),
+                status : Style::fg(Color::White),
+                warning : Style { fg : Some(Color::Black), bg : Some(Color::Yellow), bold : true, reverse : false },
+                error : Style { fg : Some(Color::White), bg : Some(Color::Red), bold : true, reverse : false },
+                progress : Style::fg(Color::Yellow).bold(),
+                visual : Style { fg : Some(Color::Black), bg : Some(Color::Cyan), bold : false, reverse : false },
+            },
+        }
+    }
+
+    /**
+        For NO_COLOR, everything that matters still stands out without color
+    **/
+    pub fn monochrome() -> Self
+    {
+        Theme {
+            border : Style::default(),
+            selected : Style::default().reverse(),
+            title : Style::default().bold(),
+            artist : Style::default(),
+            status : Style::default(),
+            warning : Style::default().bold(),
+            error : Style::default().bold(),
+            progress : Style::default().bold(),
+            visual : Style::default().reverse(),
+        }
+    }
+
+    /**
+        The preset, or the monochrome theme when there are no colors to show
+    **/
+    pub fn for_depth(preset : Preset, depth : ColorDepth) -> Self
+    {
+        if depth == ColorDepth::Monochrome {
+            Theme::monochrome()
+        } else {
+            Theme::preset(preset)
+        }
+    }
+
+    fn style_mut(&mut self, name : &str) -> Option<&mut Style>
+    {
+        match name
+        {
+            "border" => Some(&mut self.border),
+            "selected" => Some(&mut self.selected),
+            "title" => Some(&mut self.title),
+            "artist" => Some(&mut self.artist),
+            "status" => Some(&mut self.status),
+            "warning" => Some(&mut self.warning),
+            "error" => Some(&mut self.error),
+            "progress" => Some(&mut self.progress),
+            "visual" => Some(&mut self.visual),
+            _ => None,
+        }
+    }
+}
+
+/**
+        A style in the config, either just a color for the text or a table
+        like { fg = "#ffaa00", bg = "236", bold = true }
+**/
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StyleConfig
+{
+    Color(String),
+    Table(StyleTable)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleTable
+{
+    fg : Option<String>,
+    bg : Option<String>,
+    #[serde(default)]
+    bold : bool,
+    #[serde(default)]
+    reverse : bool,
+}
+
+impl StyleConfig
+{
+    fn to_style(&self) -> Result<Style, String>
+    {
+        match self
+        {
+            StyleConfig::Color(color) => Ok(Style::fg(parse_color(color)?)),
+            StyleConfig::Table(table) => Ok(Style {
+                fg : table.fg.as_deref().map(parse_color).transpose()?,
+                bg : table.bg.as_deref().map(parse_color).transpose()?,
+                bold : table.bold,
+                reverse : table.reverse,
+            }),
+        }
+    }
+}
+
+// no deny_unknown_fields since flatten doesnt work with it, a misspelt
+// key ends up with the styles and gets caught there
+#[derive(Deserialize, Default)]
+pub struct ThemeConfig
+{
+    preset : Option<Preset>,
+    colors : Option<ColorSupport>,
+    // the named styles, each one replaces the one from the preset
+    #[serde(flatten)]
+    styles : std::collections::BTreeMap<String, StyleConfig>,
+}
+
+impl ThemeConfig
+{
+    /**
+        The theme and how many colors to draw it with, colors = "auto"
+        or leaving it out goes with what the environment says
+    **/
+    pub fn to_theme(&self) -> Result<(Theme, ColorDepth), String>
+    {
+        let depth = match self.colors.unwrap_or(ColorSupport::Auto)
+        {
+            ColorSupport::Auto => ColorDepth::detect(),
+            ColorSupport::Truecolor => ColorDepth::TrueColor,
+            ColorSupport::Ansi256 => ColorDepth::Ansi256,
+            ColorSupport::Basic => ColorDepth::Basic,
+            ColorSupport::None => ColorDepth::Monochrome,
+        };
+        Ok((self.with_preset(self.preset.unwrap_or_default(), depth)?, depth))
+    }
+
+    /**
+        Any preset with the styles from the config on top, so :theme can
+        switch presets without losing them
+    **/
+    pub fn with_preset(&self, preset : Preset, depth : ColorDepth) -> Result<Theme, String>
+    {
+        let mut theme = Theme::for_depth(preset, depth);
+        for (name, style) in &self.styles
+        {
+            let slot = theme.style_mut(name).ok_or_else(|| format!("unknown style \"{}\", the styles are {}", name, STYLE_NAMES.join(", ")))?;
+            *slot = style.to_style().map_err(|e| format!("{}: {}", name, e))?;
+        }
+        Ok(theme)
+    }
+}
+
+// the names colors go by in the config, the plain ones are the bright
+// versions like crossterm has them and dark_ gets the normal ones
+const COLOR_NAMES : &[(&str, Color)] = &[
+    ("default", Color::Reset),
+    ("black", Color::Black),
+    ("dark_grey", Color::DarkGrey),
+    ("dark_gray", Color::DarkGrey),
+    ("grey", Color::Grey),
+    ("gray", Color::Grey),
+    ("white", Color::White),
+    ("red", Color::Red),
+    ("dark_red", Color::DarkRed),
+    ("green", Color::Green),
+    ("dark_green", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("dark_yellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("dark_blue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("dark_magenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("dark_cyan", Color::DarkCyan),
+];
+
+/**
+        A color name, a 256 color number or a #rrggbb hex color
+**/
+pub fn parse_color(text : &str) -> Result<Color, String>
+{
+    let lower = text.trim().to_lowercase();
+
+    if let Some(hex) = lower.strip_prefix('#') {
+        let channel = |i : usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(format!("\"{}\" is not a #rrggbb color", text)),
+        };
+    }
+    if let Ok(n) = lower.parse::<u8>() {
+        return Ok(Color::AnsiValue(n));
+    }
+
+    COLOR_NAMES.iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, color)| *color)
+        .ok_or_else(|| format!("unknown color \"{}\", use a name like dark_blue, a number up to 255 or #rrggbb", text))
+}
+
+// the levels each channel of the 6x6x6 cube in the 256 colors steps through
+const CUBE_LEVELS : [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// roughly what terminals show for the 16 basic colors, in order
+const BASIC_COLORS : [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance(a : (u8, u8, u8), b : (u8, u8, u8)) -> u32
+{
+    let d = |x : u8, y : u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/**
+        The closest of the 256 colors, whichever of the cube and the grey ramp is nearer
+**/
+fn rgb_to_ansi256(r : u8, g : u8, b : u8) -> u8
+{
+    let level = |c : u8| (0..6).min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs()).unwrap_or(0);
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = 16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8;
+    let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let grey = 8 + 10 * grey_index;
+
+    if distance((grey, grey, grey), (r, g, b)) < distance(cube_rgb, (r, g, b)) {
+        232 + grey_index
+    } else {
+        cube
+    }
+}
+
+fn ansi256_to_rgb(n : u8) -> (u8, u8, u8)
+{
+    match n
+    {
+        0..=15 => BASIC_COLORS[n as usize].1,
+        16..=231 => {
+            let i = n - 16;
+            (CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[(i / 6 % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+        }
+        _ => {
+            let grey = 8 + 10 * (n - 232);
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn nearest_basic(r : u8, g : u8, b : u8) -> Color
+{
+    BASIC_COLORS.iter().min_by_key(|(_, rgb)| distance(*rgb, (r, g, b))).map(|(color, _)| *color).unwrap_or(Color::White)
+}
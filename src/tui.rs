@@ -1,43 +1,36 @@
 use std::{
     error::Error,
+    fs,
+    future::Future,
     io::stdout,
-    io::Write,
-    time::Duration,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant
+    },
 };
-use std::sync::{Mutex, MutexGuard};
 use crossterm::{
-    cursor::{
-        MoveTo,
-        EnableBlinking
-    },
-    terminal::{
-        self,
-        Clear, 
-        ClearType,
-        enable_raw_mode,
-        disable_raw_mode
-    },
-    style::{
-        Print,
-        SetForegroundColor,
-        ResetColor,
-        Color
-    },
     event::{
-        poll,
-        read,
         Event,
-        KeyCode,
+        EventStream,
+        KeyEvent,
         KeyEventKind,
-        KeyModifiers
-    },
-    ExecutableCommand
+        MouseButton,
+        MouseEvent,
+        MouseEventKind
+    }
+};
+use futures::StreamExt;
+use image::DynamicImage;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{interval, sleep_until}
 };
-use tokio::time::{interval, sleep};
-use once_cell::sync::Lazy;
 
 
-/** 
+/* 
 
 Basic TUI Setup will be middle current song playing
 a pause skip and back thats it
@@ -47,15 +40,43 @@ This is meant to be ran in a tmux pane or a seperate terminal window
 **/
 
 use super::SpotifyClientAuth;
-
-#[derive(PartialEq)]
-pub enum TuiState
-{
-    CommandMode,
-    NormalMode
-}
-
-#[derive(PartialEq)]
+use super::spotify_client_auth::{PlayingType, RepeatState, SpotifyCurrentPlaying, SpotifyDevice, SpotifyPlayerState, SpotifyPlaylist, SpotifyTrack};
+use super::views::{self, View, ViewState, ViewTarget};
+use super::lyrics::{Lyrics, LyricsManager};
+use super::album_art::AlbumArt;
+use super::renderer::{Frame, Renderer, Style};
+use super::layout::{Layout, LayoutNode, LayoutPreset, Pane, Rect};
+use super::config::{Config, ControlMode, TitleOverflow};
+use super::text;
+use super::line_editor::{EditResult, LineEditor};
+use super::paths;
+use super::keymap::{self, Action, KeyChord, KeyLookup, Keymap};
+use super::terminal::{self, Signals, TerminalGuard, TerminalSignal};
+use super::commands::{self, ArgKind, CommandId, ParsedCommand};
+use super::mode::{Mode, ModeStack, KEYMAP_MODES};
+use super::notifications::{Level, Notification, Notifications};
+use super::status_bar::{self, Connection, Segment, StatusBar};
+use super::logging::{error, info, warn};
+use super::theme::{self, ColorDepth, Preset, Theme, ThemeConfig};
+
+// how often a scrolling title moves along one character
+const MARQUEE_STEP_MS : u64 = 300;
+// space between the label of a control and its box
+const CONTROL_PADDING : u16 = 1;
+// how much one notch of the scroll wheel moves the volume
+const VOLUME_STEP : i64 = 5;
+// files sourcing files stop here, so two that source each other dont go forever
+const SOURCE_DEPTH_LIMIT : usize = 16;
+// how long the devices and playlists completed after a : are kept before they are fetched again
+const DEVICES_TTL : Duration = Duration::from_secs(30);
+const PLAYLISTS_TTL : Duration = Duration::from_secs(300);
+// goes in between the segments of the status bar
+const SEGMENT_SEPARATOR : &str = " │ ";
+
+// a spawned request that hasnt started yet
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[derive(PartialEq, Clone, Copy)]
 pub enum Control
 {
     Pause,
@@ -63,54 +84,297 @@ pub enum Control
     SkipForward
 }
 
+// the order the controls are drawn and cycled through in
+const CONTROLS : [Control; 3] = [Control::SkipBack, Control::Pause, Control::SkipForward];
+
+impl Control
+{
+    /**
+        The control to the right, wrapping round at the end
+    **/
+    pub fn next(&self) -> Self
+    {
+        let i = CONTROLS.iter().position(|c| c == self).unwrap_or(0);
+        CONTROLS[(i + 1) % CONTROLS.len()]
+    }
+
+    pub fn previous(&self) -> Self
+    {
+        let i = CONTROLS.iter().position(|c| c == self).unwrap_or(0);
+        CONTROLS[(i + CONTROLS.len() - 1) % CONTROLS.len()]
+    }
+
+    pub fn label(&self) -> &'static str
+    {
+        match self
+        {
+            Control::Pause => "Pause",
+            Control::SkipBack => "<-",
+            Control::SkipForward => "->",
+        }
+    }
+}
+
+/**
+        What the background tasks send back to the ui, errors are turned
+        into strings since they have to cross over from another task
+**/
+pub enum AppMessage
+{
+    CurrentPlaying(Result<(SpotifyPlayerState, Option<SpotifyCurrentPlaying>), String>),
+    // how the last poll went, for the network segment
+    Connection(Connection),
+    Profile(Result<String, String>),
+    Queue(Result<Vec<SpotifyTrack>, String>),
+    AlbumArt(String, Result<DynamicImage, String>),
+    // the artist and title they were looked up for, along with what was found
+    Lyrics((String, String), Result<Option<Lyrics>, String>),
+    ViewLoaded(ViewTarget, Result<View, String>),
+    Devices(Result<Vec<SpotifyDevice>, String>),
+    Playlists(Result<Vec<SpotifyPlaylist>, String>),
+    // something to tell the user about, shown for a few seconds
+    Notify(Level, String),
+    // something too long for a notification, shown in a box until dismissed
+    Popup(String, String),
+    // something went wrong in the background, shown as an error and logged
+    Failed(String),
+    // we paused, skipped or seeked so the current song is out of date, along with what we did
+    PlaybackChanged(String)
+}
+
+impl AppMessage
+{
+    /**
+        Turns the result of a playback request into the message to send back
+    **/
+    fn playback(result : Result<String, Box<dyn Error>>, action : &str) -> Self
+    {
+        match result {
+            Ok(done) => AppMessage::PlaybackChanged(done),
+            Err(e) => AppMessage::Failed(format!("{} failed: {}", action, e)),
+        }
+    }
+
+    /**
+        Puts the file:line of a sourced line in front of an error so it
+        still says where it came from when it shows up later
+    **/
+    fn located(self, location : &str) -> Self
+    {
+        match self
+        {
+            AppMessage::Failed(e) => AppMessage::Failed(format!("{}: {}", location, e)),
+            AppMessage::Notify(Level::Error, e) => AppMessage::Notify(Level::Error, format!("{}: {}", location, e)),
+            message => message,
+        }
+    }
+}
+
 
 pub struct Tui
 {
-    pub auth : SpotifyClientAuth,
-    pub state : TuiState,
+    // shared with the background tasks, requests only wait on each other for a token refresh
+    auth : Arc<SpotifyClientAuth>,
+    sender : UnboundedSender<AppMessage>,
+    // taken by start, the loop is the only thing reading it
+    receiver : Option<UnboundedReceiver<AppMessage>>,
+    // a poll is in flight so the next tick doesnt start another one
+    polling : bool,
+    mode : ModeStack,
     running : bool,
-    control : Control
+    // stopped by Ctrl-Z, nothing gets drawn until SIGCONT sets the terminal up again
+    suspended : bool,
+    control : Control,
+    current : Option<SpotifyCurrentPlaying>,
+    // the device, volume and shuffle that came with the last poll
+    player_state : SpotifyPlayerState,
+    connection : Connection,
+    // name of the signed in account
+    profile : Option<String>,
+    view_stack : Vec<ViewState>,
+    // shared with the task looking them up
+    lyrics_manager : Arc<LyricsManager>,
+    lyrics : Option<Lyrics>,
+    // artist and title the lyrics were looked up for
+    lyrics_key : Option<(String, String)>,
+    show_lyrics : bool,
+    album_art : AlbumArt,
+    // when current was fetched, used to guess the progress in between polls
+    fetched_at : Instant,
+    renderer : Renderer,
+    // what is being typed after a :
+    command_line : LineEditor,
+    // what is being typed after a /
+    search_line : LineEditor,
+    last_search : Option<String>,
+    // the selection before the search started, esc goes back to it
+    search_origin : usize,
+    // the row visual mode started on, the selection is the other end
+    visual_anchor : usize,
+    help_scroll : usize,
+    // title and text of the box shown in popup mode
+    popup : Option<(String, String)>,
+    // the file :source is running, relative paths in it start from its folder
+    sourcing : Option<PathBuf>,
+    source_depth : usize,
+    // file:line of the line being sourced, the tasks it starts put it in front of their errors
+    source_location : Option<String>,
+    // tasks started by sourced lines go here and run one after another, so
+    // a :device before a :volume in the rc happens in that order
+    sourced_tasks : UnboundedSender<Task>,
+    // names to complete :device and :playlist with, refreshed when the command line opens
+    devices : Vec<SpotifyDevice>,
+    playlists : Vec<SpotifyPlaylist>,
+    // when each was last asked for, so opening the : line over and over doesnt refetch them
+    devices_fetched : Option<Instant>,
+    playlists_fetched : Option<Instant>,
+    // short lived messages stacked at the bottom
+    notifications : Notifications,
+    layout : LayoutNode,
+    layout_preset : LayoutPreset,
+    keymap : Keymap,
+    // the start of a key sequence like gg waiting for the rest
+    pending_keys : Vec<KeyChord>,
+    // the layout of the last frame, clicks get matched against it
+    rendered_layout : Option<Layout>,
+    queue : Vec<SpotifyTrack>,
+    title_overflow : TitleOverflow,
+    control_mode : ControlMode,
+    theme : Theme,
+    // the [theme] styles, they stay when :theme changes the preset
+    theme_config : ThemeConfig,
+    color_depth : ColorDepth,
+    status_bar : StatusBar,
+    // when the playing title last changed, the marquee starts over from here
+    title_changed_at : Instant
 }
 
-static LAST_TRACK : Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
-static LAST_WIDTH : Lazy<Mutex<u16>> = Lazy::new(|| Mutex::new(8));
-
 impl Tui 
 {
-    pub fn new(auth: SpotifyClientAuth) -> Self
+    pub fn new(auth: SpotifyClientAuth, config : Config) -> Self
     {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (sourced_tasks, mut sourced_receiver) = mpsc::unbounded_channel::<Task>();
+        tokio::spawn(async move {
+            while let Some(task) = sourced_receiver.recv().await
+            {
+                task.await;
+            }
+        });
+
         Tui
         {
-            auth,
-            state : TuiState::NormalMode,
+            auth : Arc::new(auth),
+            sender,
+            receiver : Some(receiver),
+            polling : false,
+            mode : ModeStack::new(),
             running : false,
-            control : Control::Pause
+            suspended : false,
+            control : Control::Pause,
+            current : None,
+            player_state : SpotifyPlayerState::default(),
+            connection : Connection::Online,
+            profile : None,
+            view_stack : Vec::new(),
+            lyrics_manager : Arc::new(LyricsManager::from_env()),
+            lyrics : None,
+            lyrics_key : None,
+            show_lyrics : true,
+            album_art : AlbumArt::new(config.color_depth),
+            fetched_at : Instant::now(),
+            renderer : Renderer::new(config.color_depth),
+            command_line : LineEditor::new(paths::state_dir().join("history")),
+            search_line : LineEditor::new(paths::state_dir().join("search_history")),
+            last_search : None,
+            search_origin : 0,
+            visual_anchor : 0,
+            help_scroll : 0,
+            popup : None,
+            sourcing : None,
+            source_depth : 0,
+            source_location : None,
+            sourced_tasks,
+            devices : Vec::new(),
+            playlists : Vec::new(),
+            devices_fetched : None,
+            playlists_fetched : None,
+            notifications : Notifications::new(),
+            layout : config.layout,
+            layout_preset : config.layout_preset,
+            keymap : config.keymap,
+            pending_keys : Vec::new(),
+            rendered_layout : None,
+            queue : Vec::new(),
+            title_overflow : config.title_overflow,
+            control_mode : config.control_mode,
+            theme : config.theme,
+            theme_config : config.theme_config,
+            color_depth : config.color_depth,
+            status_bar : config.status_bar,
+            title_changed_at : Instant::now()
         }
     }
 
     pub async fn start(&mut self) -> Result<(), Box<dyn Error>>
     {
-        stdout().execute(Clear(ClearType::All))?;
-        stdout().execute(MoveTo(0,0))?;
-        stdout().execute(EnableBlinking)?;
-        // hide the cursor
-
-        enable_raw_mode()?;
+        // puts the terminal back when this returns, early or not
+        let _terminal = TerminalGuard::enter()?;
+        let mut signals = Signals::new()?;
         self.running = true;
 
+        let mut receiver = self.receiver.take().ok_or("the tui can only be started once")?;
+        self.source_rc();
+        if self.status_bar.has(Segment::Profile) {
+            self.spawn(|auth, sender| async move {
+                let profile = auth.get_profile().await.map_err(|e| e.to_string());
+                let _ = sender.send(AppMessage::Profile(profile));
+            });
+        }
+        let mut events = EventStream::new();
         let mut song_update_interval = interval(Duration::from_secs(5)); // Update every 5 seconds
+        // only ticks while a playing title is too long for its pane
+        let mut marquee_interval = interval(Duration::from_millis(MARQUEE_STEP_MS));
+        // redraws so the progress bar moves along in between polls
+        let mut progress_interval = interval(Duration::from_secs(1));
 
-        // main loop
+        // main loop, nothing in here waits on spotify, requests go off in
+        // their own tasks and come back through the receiver
         loop
         {
+            // wakes up when the oldest notification runs out so it goes away on time
+            let next_expiry = self.notifications.next_expiry();
 
             tokio::select!
             {
-                _ = self.handle_input() => {
-
+                event = events.next() => {
+                    match event {
+                        Some(event) => self.handle_event(event?),
+                        // stdin is gone so there is nobody left to read keys from
+                        None => self.running = false,
+                    }
+                },
+                Some(message) = receiver.recv() => {
+                    self.handle_message(message);
                 },
                 _ = song_update_interval.tick() => {
-                    self.render_current_playing().await?;
+                    self.poll_current_playing();
+                }
+                _ = marquee_interval.tick(), if self.needs_marquee() => {}
+                _ = progress_interval.tick(), if self.needs_ticking() => {}
+                _ = sleep_until(next_expiry.unwrap_or_else(Instant::now).into()), if next_expiry.is_some() => {
+                    self.notifications.expire();
+                }
+                signal = signals.recv() => {
+                    match signal {
+                        TerminalSignal::Quit => self.running = false,
+                        TerminalSignal::Suspend => self.suspend(),
+                        TerminalSignal::Continue => {
+                            terminal::setup()?;
+                            self.suspended = false;
+                            self.renderer.invalidate();
+                        }
+                    }
                 }
             }
 
@@ -118,386 +382,1998 @@ impl Tui
             {
                 break;
             }
-            
-            self.render_skip_back(4, 1, 0).await?;
-            self.render_pause(4, 1, 6).await?;
-            self.render_skip_forward(4, 1, 15).await?;
 
-            self.render_state()?;
+            if self.suspended
+            {
+                continue;
+            }
+
+            // the whole screen is composed every pass, the renderer only sends what changed
+            self.draw()?;
         }
 
-        // clear the terminal
-        stdout().execute(Clear(ClearType::All))?;
-        // disable raw mode
-        disable_raw_mode()?;
         Ok(())
     }
 
-    fn render_box_around_text(&self, start_index : u16, padding : u16, display_str : String, start_x : u16) -> Result<(), Box<dyn Error>>
+    /**
+        Builds a frame of the whole screen and hands it to the renderer,
+        the layout gets resolved again every time so it follows the terminal size
+    **/
+    pub fn draw(&mut self) -> Result<(), Box<dyn Error>>
     {
-        stdout().execute(MoveTo((start_x + 1) + padding, start_index))?; // center of the screen
-        stdout().execute(Print(&display_str))?;
+        let (width, height) = crossterm::terminal::size()?;
+        // some terminals report 0 rows while they are being set up, there is nowhere to draw
+        if height == 0 {
+            return Ok(());
+        }
+        let mut frame = Frame::new(width, height);
 
-        // add a | to the left of the pause
-        stdout().execute(MoveTo(start_x, start_index))?;
-        stdout().execute(Print("│"))?;
-        // add a | to the right of the pause
-        stdout().execute(MoveTo(start_x + (display_str.len() as u16 + 1) + (padding * 2) , start_index))?;
-        stdout().execute(Print("│"))?;
-        
-        // add a top left corner
-        stdout().execute(MoveTo(start_x, start_index - 1))?;
-        stdout().execute(Print("┌"))?;
-        // add a top right corner
-        stdout().execute(MoveTo(start_x + (display_str.len() as u16 + 1) + (padding * 2) , start_index - 1))?;
-        stdout().execute(Print("┐"))?;
+        let preset = self.layout_preset.pick(height);
+        if width < preset.min_width() || height < preset.min_height() {
+            Tui::render_too_small(&mut frame, preset.min_width(), preset.min_height());
+            self.renderer.render(frame, &mut stdout())?;
+            self.rendered_layout = None;
+            return Ok(());
+        }
 
-        // add a bottom left corner
-        stdout().execute(MoveTo(start_x, start_index + 1))?;
-        stdout().execute(Print("└"))?;
-        // add a bottom right corner
-        stdout().execute(MoveTo(start_x + (display_str.len() as u16 + 1) + (padding * 2) , start_index + 1))?;
-        stdout().execute(Print("┘"))?;
+        let layout = self.resolve_layout(width, height);
 
-        // add a - to the top of the pause
-        stdout().execute(MoveTo(start_x + 1, start_index - 1))?;
-        for _ in 1..display_str.len() as u16 + 1 + (padding * 2)
-        {
-            stdout().execute(Print("─"))?;
+        let art = layout.get(Pane::AlbumArt).and_then(|rect| self.art_rect(rect));
+        if let Some(art) = art {
+            if let Err(e) = self.album_art.prepare(art.width, art.height) {
+                self.notify(Level::Warn, format!("Album art failed: {}", e));
+            }
         }
-        // add a - to the bottom of the pause
-        stdout().execute(MoveTo(start_x + 1, start_index + 1))?;
-        for _ in 1..display_str.len() as u16 + 1 + (padding * 2)
-        {
-            stdout().execute(Print("─"))?;
+
+        // keep the selection on screen before the view gets drawn
+        if let (Some(rect), Some(view_state)) = (layout.get(Pane::Library), self.view_stack.last_mut()) {
+            view_state.scroll_to_selection(rect.height.saturating_sub(1) as usize);
         }
 
+        if let Some(rect) = layout.get(Pane::NowPlaying) {
+            self.render_current_playing(&mut frame, rect);
+        }
+        if let Some(art) = art {
+            self.album_art.draw(&mut frame, art.x, art.y);
+        }
+        if let Some(rect) = layout.get(Pane::Controls) {
+            self.render_controls(&mut frame, rect);
+        }
+        if let Some(rect) = layout.get(Pane::Library) {
+            self.render_view(&mut frame, rect);
+        }
+        if let Some(rect) = layout.get(Pane::Queue) {
+            self.render_queue(&mut frame, rect);
+        }
+        if let Some(rect) = layout.get(Pane::MiniPlayer) {
+            self.render_mini_player(&mut frame, rect);
+        }
+        if let Some(rect) = layout.get(Pane::Progress) {
+            self.render_progress(&mut frame, rect);
+        }
+        if let Some(rect) = self.notification_area(&layout, width, height) {
+            self.render_notifications(&mut frame, rect);
+        }
+        if let Some(rect) = layout.get(Pane::Status) {
+            self.render_state(&mut frame, rect);
+        } else if matches!(self.mode.current(), Mode::Command | Mode::Search) {
+            // without a status pane the line being typed takes over the bottom row
+            let rect = Rect::new(0, height.saturating_sub(1), width, 1);
+            frame.set_string(0, rect.y, &" ".repeat(width as usize), Style::default());
+            self.render_state(&mut frame, rect);
+        }
+
+        // overlays go over everything else, the popup over the help
+        if self.mode.contains(Mode::Help) {
+            let lines = self.help_lines();
+            // the overlay leaves a row above and below and has its borders
+            let visible = frame.height.saturating_sub(4) as usize;
+            self.help_scroll = self.help_scroll.min(lines.len().saturating_sub(visible));
+            self.render_overlay(&mut frame, "Help", &lines, self.help_scroll);
+        }
+        if let Some((title, text)) = &self.popup {
+            let width = frame.width.saturating_sub(8).min(72) as usize;
+            self.render_overlay(&mut frame, title, &text::wrap(text, width), 0);
+        }
+
+        self.renderer.render(frame, &mut stdout())?;
+        self.rendered_layout = Some(layout);
         Ok(())
     }
 
-    pub async fn render_pause(&self, start_index : u16, padding : u16, start_x : u16) -> Result<(), Box<dyn Error>>
+    /**
+        Replaces everything with a notice until the terminal is big enough
+        again for the layout that got picked
+    **/
+    fn render_too_small(frame : &mut Frame, min_width : u16, min_height : u16)
     {
-        if self.control == Control::Pause
+        let size = format!("{}x{}, need {}x{}", frame.width, frame.height, min_width, min_height);
+        // a single row only fits a single line
+        let lines = if frame.height < 2 {
+            vec![format!("Too small: {}", size)]
+        } else {
+            vec!["Terminal too small".to_string(), size]
+        };
+
+        let top = frame.height.saturating_sub(lines.len() as u16) / 2;
+        for (i, line) in lines.iter().enumerate()
         {
-            // set foreground to green
-            stdout().execute(SetForegroundColor(Color::Green))?;
+            let line = text::truncate(line, frame.width as usize);
+            let x = text::center_offset(&line, frame.width as usize) as u16;
+            frame.set_string(x, top + i as u16, &line, Style::default());
         }
+    }
 
-        self.render_box_around_text(start_index, padding, "Pause".to_string(), start_x)?;
+    /**
+        A resize makes the terminal reflow whatever was on it, so the next
+        draw goes out from scratch for the new size
+    **/
+    fn handle_resize(&mut self)
+    {
+        self.renderer.invalidate();
+    }
 
-        // reset Color
-        stdout().execute(ResetColor)?;
-        stdout().flush()?;
+    /**
+        Stops until the shell brings us back, the SIGCONT that wakes us sets
+        the terminal up and redraws everything
+    **/
+    fn suspend(&mut self)
+    {
+        terminal::suspend();
+        self.suspended = true;
+    }
 
-        Ok(())
+    /**
+        Runs a spotify request off in its own task so the ui keeps going,
+        whatever it wants to report goes back through the channel, one
+        started by a sourced line waits for the ones before it
+    **/
+    fn spawn<F, Fut>(&self, task : F)
+    where
+        F : FnOnce(Arc<SpotifyClientAuth>, UnboundedSender<AppMessage>) -> Fut,
+        Fut : Future<Output = ()> + Send + 'static
+    {
+        let Some(location) = self.source_location.clone() else {
+            tokio::spawn(task(self.auth.clone(), self.sender.clone()));
+            return;
+        };
+
+        // what the task sends goes through here first to pick up where it came from
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let task = task(self.auth.clone(), sender);
+        let app_sender = self.sender.clone();
+        let _ = self.sourced_tasks.send(Box::pin(async move {
+            task.await;
+            while let Some(message) = receiver.recv().await
+            {
+                let _ = app_sender.send(message.located(&location));
+            }
+        }));
+    }
+
+    /**
+        Shows a notification, it goes away on its own after a few seconds
+    **/
+    fn notify(&mut self, level : Level, text : String)
+    {
+        self.notifications.push(level, text);
+    }
+
+    /**
+        Asks spotify for the current song in the background, the queue
+        comes along with it when that pane is on screen
+    **/
+    fn poll_current_playing(&mut self)
+    {
+        // spotify asked us to wait so the polls hold off until then
+        if self.polling || self.connection.is_limited() {
+            return;
+        }
+        self.polling = true;
+
+        let with_queue = self.layout_has(Pane::Queue);
+        self.spawn(move |auth, sender| async move {
+            // the error gets looked at before it turns into a string, in its own block
+            // since it cant be held on to across the next request
+            let current = {
+                let current = auth.get_current_playing().await;
+                let connection = match &current {
+                    Ok(_) => Connection::Online,
+                    Err(e) => Connection::from_error(e.as_ref()),
+                };
+                let _ = sender.send(AppMessage::Connection(connection));
+                current.map_err(|e| e.to_string())
+            };
+            let _ = sender.send(AppMessage::CurrentPlaying(current));
+
+            if with_queue {
+                let queue = auth.get_queue().await.map_err(|e| e.to_string());
+                let _ = sender.send(AppMessage::Queue(queue));
+            }
+        });
     }
 
-    pub async fn render_skip_back(&self, start_index : u16, padding : u16, start_x : u16) -> Result<(), Box<dyn Error>>
+    /**
+        Takes in whatever a background task finished with
+    **/
+    fn handle_message(&mut self, message : AppMessage)
     {
-        if self.control == Control::SkipBack
+        match message
         {
-            // set foreground to green
-            stdout().execute(SetForegroundColor(Color::Green))?;
+            AppMessage::CurrentPlaying(Ok((player_state, current))) => {
+                self.polling = false;
+                self.player_state = player_state;
+                self.update_current_playing(current);
+            }
+            AppMessage::CurrentPlaying(Err(e)) => {
+                self.polling = false;
+                warn!("failed to get the current song: {}", e);
+            }
+            AppMessage::Connection(connection) => self.connection = connection,
+            AppMessage::Profile(Ok(profile)) => self.profile = Some(profile),
+            AppMessage::Profile(Err(e)) => warn!("failed to get the profile: {}", e),
+            AppMessage::Queue(Ok(queue)) => self.queue = queue,
+            AppMessage::Queue(Err(e)) => warn!("queue failed: {}", e),
+            AppMessage::AlbumArt(url, Ok(image)) => self.album_art.set_image(&url, image),
+            AppMessage::AlbumArt(_, Err(e)) => warn!("album art failed: {}", e),
+            // the track may have changed again while they were being looked up
+            AppMessage::Lyrics(key, result) if self.lyrics_key.as_ref() == Some(&key) => match result {
+                Ok(lyrics) => self.lyrics = lyrics,
+                Err(e) => warn!("lyrics lookup failed: {}", e),
+            },
+            AppMessage::Lyrics(..) => {}
+            AppMessage::ViewLoaded(target, Ok(view)) => {
+                if !self.view_stack.last().is_some_and(|v| v.view.shows(&target)) {
+                    // a search or selection was in the view that just got covered
+                    self.leave_mode(Mode::Search);
+                    self.leave_mode(Mode::Visual);
+                    self.view_stack.push(ViewState::new(view));
+                }
+            }
+            AppMessage::ViewLoaded(_, Err(e)) => self.notify(Level::Error, format!("Failed to open view: {}", e)),
+            AppMessage::Devices(Ok(devices)) => self.devices = devices,
+            AppMessage::Devices(Err(e)) => warn!("failed to get devices: {}", e),
+            AppMessage::Playlists(Ok(playlists)) => self.playlists = playlists,
+            AppMessage::Playlists(Err(e)) => warn!("failed to get playlists: {}", e),
+            AppMessage::Notify(level, text) => self.notify(level, text),
+            AppMessage::Popup(title, text) => {
+                self.popup = Some((title, text));
+                self.enter_mode(Mode::Popup);
+            }
+            AppMessage::Failed(e) => {
+                error!("{}", e);
+                self.notify(Level::Error, e);
+            }
+            AppMessage::PlaybackChanged(done) => {
+                self.notify(Level::Info, done);
+                self.poll_current_playing();
+            }
         }
-        
-        self.render_box_around_text(start_index, padding, "<-".to_string(), start_x)?;
+    }
 
-        // reset Color
-        stdout().execute(ResetColor)?;
-        stdout().flush()?;
-        Ok(())
+    /**
+        Swaps in a freshly polled song and loads whatever hangs off of it
+    **/
+    fn update_current_playing(&mut self, current : Option<SpotifyCurrentPlaying>)
+    {
+        // we hold on to it so the detail views know what to open
+        let previous_song = self.current.as_ref().map(|c| c.song.clone());
+        self.current = current;
+        self.fetched_at = Instant::now();
+        if self.current.as_ref().map(|c| c.song.clone()) != previous_song {
+            self.title_changed_at = Instant::now();
+        }
+        self.update_lyrics();
+
+        let image_url = self.current.as_ref().and_then(|c| c.image_url.clone());
+        let needs_fetch = self.album_art.set_url(image_url.as_deref());
+        if let Some(url) = image_url.filter(|_| needs_fetch) {
+            let cache_dir = self.album_art.cache_dir();
+            self.spawn(move |_, sender| async move {
+                let image = AlbumArt::fetch(cache_dir, &url).await.map_err(|e| e.to_string());
+                let _ = sender.send(AppMessage::AlbumArt(url, image));
+            });
+        }
     }
 
-    pub async fn render_skip_forward(&self, start_index : u16, padding : u16, start_x : u16) -> Result<(), Box<dyn Error>>
+    /**
+        Panes with nothing to show give their space to the rest of the layout
+    **/
+    fn resolve_layout(&self, width : u16, height : u16) -> Layout
     {
-        if self.control == Control::SkipForward
+        let mut hidden : Vec<Pane> = Vec::new();
+        if !self.album_art.has_image() {
+            hidden.push(Pane::AlbumArt);
+        }
+        let preset_node;
+        let node = match self.layout_preset.pick(height)
+        {
+            LayoutPreset::OneLine => {
+                preset_node = LayoutNode::one_line_layout();
+                &preset_node
+            }
+            LayoutPreset::Compact => {
+                preset_node = LayoutNode::compact_layout();
+                &preset_node
+            }
+            LayoutPreset::Auto | LayoutPreset::Full => &self.layout,
+        };
+
+        let layout = Layout::new(node, width, height, &hidden);
+        match self.notification_area(&layout, width, height)
         {
-            // set foreground to green
-            stdout().execute(SetForegroundColor(Color::Green))?;
+            // the panes make room for the notifications instead of being drawn over
+            Some(area) if area.y > 0 => Layout::new(node, width, area.y, &hidden),
+            Some(area) => Layout::new(node, area.x, height, &hidden),
+            None => layout,
         }
+    }
 
-        self.render_box_around_text(start_index, padding, "->".to_string(), start_x)?;
+    /**
+        How many rows the notifications get at the bottom, one each for as
+        many as the layout can spare without going under what it needs
+    **/
+    fn notification_rows(&self, height : u16) -> u16
+    {
+        let count = self.notifications.visible().count() as u16;
+        count.min(height.saturating_sub(self.layout_preset.pick(height).min_height()))
+    }
 
-        // Reset Color
-        stdout().execute(ResetColor)?;
-        stdout().flush()?;
-        Ok(())
+    /**
+        Where the notifications go, the rows they get or when there are
+        none to spare the bottom row or with only one row the right end of
+        it, None when the newest one goes in the status row instead
+    **/
+    fn notification_area(&self, layout : &Layout, width : u16, height : u16) -> Option<Rect>
+    {
+        let notification = self.notifications.latest()?;
+        let rows = self.notification_rows(height);
+        if rows > 0 {
+            return Some(Rect::new(0, height - rows, width, rows));
+        }
+        if layout.get(Pane::Status).is_some() || height == 0 {
+            return None;
+        }
+        if height > 1 {
+            return Some(Rect::new(0, height - 1, width, 1));
+        }
+        let text_width = text::display_width(&notification.display()) as u16 + 2;
+        let row_width = text_width.min(width / 2);
+        Some(Rect::new(width - row_width, 0, row_width, 1))
     }
 
+    fn layout_has(&self, pane : Pane) -> bool
+    {
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        self.resolve_layout(width, height).get(pane).is_some()
+    }
 
-    pub async fn render_current_playing(&mut self) -> Result<(), Box<dyn Error>>
+    fn render_box_around_text(&self, frame : &mut Frame, start_index : u16, padding : u16, display_str : String, start_x : u16, style : Style)
     {
-        // Get the current song
-        let current_track = match self.auth.get_current_playing().await? {
-            Some(track) => format!("🎵 {} - {}", track.song, track.artists),
-            None => "🎵 No song playing".to_string(),
-        };
+        frame.set_string((start_x + 1) + padding, start_index, &display_str, style); // center of the screen
+
+        let right_x = start_x + (text::display_width(&display_str) as u16 + 1) + (padding * 2);
 
-        // Get terminal height for centering
-        let (width, _) = terminal::size()?;
+        // add a | to the left and right of the text
+        frame.set_cell(start_x, start_index, "│", style);
+        frame.set_cell(right_x, start_index, "│", style);
 
-        // Lock The Mutex before doing anything with it
-        let mut last_track : MutexGuard<Option<String>>= LAST_TRACK.lock().unwrap();
-        let mut last_width : MutexGuard<u16> = LAST_WIDTH.lock().unwrap();
-        // highest check for a redraw should be if the width is changed
-        if *last_width != width
+        // add the corners
+        frame.set_cell(start_x, start_index.saturating_sub(1), "┌", style);
+        frame.set_cell(right_x, start_index.saturating_sub(1), "┐", style);
+        frame.set_cell(start_x, start_index + 1, "└", style);
+        frame.set_cell(right_x, start_index + 1, "┘", style);
+
+        // add a - to the top and bottom of the text
+        for x in start_x + 1..right_x
         {
-            *last_width = width;
-            *last_track = Some(current_track.clone());
+            frame.set_cell(x, start_index.saturating_sub(1), "─", style);
+            frame.set_cell(x, start_index + 1, "─", style);
+        }
+    }
+
+    /**
+        Selected controls get drawn in green
+    **/
+    fn control_style(&self, control : Control) -> Style
+    {
+        if self.control == control {
+            self.theme.selected
+        } else {
+            self.theme.border
         }
-        // If the song hasn't changed, don't redraw
-        else if *last_track == Some(current_track.clone())
+    }
+
+    /**
+        The three control boxes side by side, each box is three rows tall
+    **/
+    fn render_controls(&self, frame : &mut Frame, rect : Rect)
+    {
+        if rect.height < 3 {
+            return;
+        }
+        for (control, area) in Tui::control_boxes(rect)
         {
-            return Ok(());
+            let start_index = area.y + 1;
+            match control
+            {
+                Control::SkipBack => self.render_skip_back(frame, start_index, CONTROL_PADDING, area.x),
+                Control::Pause => self.render_pause(frame, start_index, CONTROL_PADDING, area.x),
+                Control::SkipForward => self.render_skip_forward(frame, start_index, CONTROL_PADDING, area.x),
+            }
         }
+    }
 
+    /**
+        Where every control box sits in the controls pane, drawing and
+        clicking both go off this so a click always lands on what was drawn
+    **/
+    fn control_boxes(rect : Rect) -> Vec<(Control, Rect)>
+    {
+        let mut x = rect.x;
+        CONTROLS.into_iter().map(|control| {
+            // the label, its padding on both sides and the two borders
+            let width = text::display_width(control.label()) as u16 + CONTROL_PADDING * 2 + 2;
+            let area = Rect::new(x, rect.y, width, 3);
+            x += width;
+            (control, area)
+        }).collect()
+    }
+
+    pub fn render_pause(&self, frame : &mut Frame, start_index : u16, padding : u16, start_x : u16)
+    {
+        let style = self.control_style(Control::Pause);
+        self.render_box_around_text(frame, start_index, padding, Control::Pause.label().to_string(), start_x, style);
+    }
+
+    pub fn render_skip_back(&self, frame : &mut Frame, start_index : u16, padding : u16, start_x : u16)
+    {
+        let style = self.control_style(Control::SkipBack);
+        self.render_box_around_text(frame, start_index, padding, Control::SkipBack.label().to_string(), start_x, style);
+    }
+
+    pub fn render_skip_forward(&self, frame : &mut Frame, start_index : u16, padding : u16, start_x : u16)
+    {
+        let style = self.control_style(Control::SkipForward);
+        self.render_box_around_text(frame, start_index, padding, Control::SkipForward.label().to_string(), start_x, style);
+    }
+
+    pub fn render_current_playing(&self, frame : &mut Frame, rect : Rect)
+    {
+        let width = rect.width;
+        if width < 2 || rect.height < 3 {
+            return;
+        }
+        let (left, top) = (rect.x, rect.y);
+        let right = rect.right().saturating_sub(1);
 
         // Calculate available width inside borders (subtract 2 for left/right borders)
         let available_width = width.saturating_sub(2);
-
-        // Truncate the song text if it's too long for the available width,
-        // reserving 3 characters for the ellipsis "..."
-        let display_track = if current_track.len() > available_width as usize {
-            let mut truncated: String = current_track.chars().take(available_width as usize - 3).collect();
-            truncated.push_str("...");
-            truncated
-        } else {
-            current_track.clone()
-        };
+        let (song_part, display_track) = self.playing_title(available_width as usize);
 
         // Calculate x position to center the text within the available width:
         // x = 1 (left border) + ((available_width - text_width) / 2)
-        let x = 1 + available_width.saturating_sub(display_track.len() as u16) / 2;
-
-        // Render the text at the calculated position (y coordinate set to 1)
-        stdout().execute(MoveTo(x, 1))?;
-        stdout().execute(Clear(ClearType::CurrentLine))?;
-        stdout().execute(Print(&display_track))?;
+        let x = left + 1 + text::center_offset(&display_track, available_width as usize) as u16;
+        self.render_title(frame, x, top + 1, &song_part, &display_track);
 
+        // the bottom border fills up as the song plays
+        let played = match (&self.current, self.estimated_progress_ms()) {
+            (Some(current), Some(progress_ms)) if current.duration_ms > 0 => {
+                (right.saturating_sub(left + 1) as u64 * progress_ms / current.duration_ms) as u16
+            }
+            _ => 0,
+        };
 
         // wanna print a box around the song
-        stdout().execute(MoveTo(1, 0))?; // top left
-        // draw a line straight to the right till 1 before the max
-        for _ in 1..width - 1
+        for x in left + 1..right
         {
-            stdout().execute(Print("─"))?;
-        }
-        stdout().execute(MoveTo(1, 2))?; // bottom left
-        // draw a line straight to the right till 1 before the max
-        for _ in 1..width - 1
-        {
-            stdout().execute(Print("─"))?;
+            frame.set_cell(x, top, "─", self.theme.border);
+            if x < left + 1 + played {
+                frame.set_cell(x, top + 2, "━", self.theme.progress);
+            } else {
+                frame.set_cell(x, top + 2, "─", self.theme.border);
+            }
         }
 
         // draw the corners will be special
-        stdout().execute(MoveTo(0, 0))?; // top left
-        stdout().execute(Print("┌"))?;
-        stdout().execute(MoveTo(width - 1, 0))?; // top right
-        stdout().execute(Print("┐"))?;
-        stdout().execute(MoveTo(0, 2))?; // bottom left
-        stdout().execute(Print("└"))?;
-        stdout().execute(MoveTo(width - 1, 2))?; // bottom right
-        stdout().execute(Print("┘"))?;
+        frame.set_cell(left, top, "┌", self.theme.border);
+        frame.set_cell(right, top, "┐", self.theme.border);
+        frame.set_cell(left, top + 2, "└", self.theme.border);
+        frame.set_cell(right, top + 2, "┘", self.theme.border);
+
+        // add a bar between the corners at the middle row
+        frame.set_cell(left, top + 1, "│", self.theme.border);
+        frame.set_cell(right, top + 1, "│", self.theme.border);
+    }
 
-        // add a bar between the corners at the 1 y
+    /**
+        The song and what comes after it fitted into width, titles that
+        dont fit either get cut off or scroll, the song part comes back on
+        its own so it can be styled apart from the artists
+    **/
+    fn playing_title(&self, width : usize) -> (String, String)
+    {
+        let (song_part, current_track) = self.full_title(width);
+        let display_track = match self.title_overflow {
+            TitleOverflow::Truncate => text::truncate(&current_track, width),
+            // a paused title sits still at its start
+            TitleOverflow::Marquee if !self.current.as_ref().is_some_and(|c| c.is_playing) => text::marquee(&current_track, width, 0),
+            TitleOverflow::Marquee => {
+                let step = self.title_changed_at.elapsed().as_millis() as u64 / MARQUEE_STEP_MS;
+                text::marquee(&current_track, width, step as usize)
+            }
+        };
+        (song_part, display_track)
+    }
+
+    /**
+        The song part and the whole title before anything gets cut off
+    **/
+    fn full_title(&self, width : usize) -> (String, String)
+    {
+        match &self.current {
+            Some(episode) if episode.playing_type == PlayingType::Episode => {
+                let song_part = format!("🎙 {} - ", episode.song);
+                let track = format!("{}{} ({})", song_part, episode.album, episode.artist_credit(usize::MAX));
+                (song_part, track)
+            }
+            Some(track) => {
+                // give the artists whatever room the song name leaves
+                let song_part = format!("🎵 {} - ", track.song);
+                let room = width.saturating_sub(text::display_width(&song_part));
+                let track = format!("{}{}", song_part, track.artist_credit(room));
+                (song_part, track)
+            }
+            None => {
+                let track = "🎵 No song playing".to_string();
+                (track.clone(), track)
+            }
+        }
+    }
 
-        stdout().execute(MoveTo(0, 1))?; // top left
-        stdout().execute(Print("│"))?;
-        stdout().execute(MoveTo(width - 1, 1))?; // top right
-        stdout().execute(Print("│"))?;
+    /**
+        How wide the title gets to be in the layout last drawn, inside the
+        borders of the now playing box or after the mini player controls
+    **/
+    fn title_width(&self, layout : &Layout) -> Option<usize>
+    {
+        if let Some(rect) = layout.get(Pane::NowPlaying) {
+            return Some(rect.width.saturating_sub(2) as usize);
+        }
+        let rect = layout.get(Pane::MiniPlayer)?;
+        let x = Tui::inline_controls(rect).last().map_or(rect.x, |(_, area)| area.right() + 1);
+        Some(rect.right().saturating_sub(x) as usize)
+    }
 
-        stdout().flush()?;
+    /**
+        Whether the marquee has anything to scroll, a playing title that
+        doesnt fit where it is drawn
+    **/
+    fn needs_marquee(&self) -> bool
+    {
+        if self.title_overflow != TitleOverflow::Marquee || !self.current.as_ref().is_some_and(|c| c.is_playing) {
+            return false;
+        }
+        let Some(width) = self.rendered_layout.as_ref().and_then(|layout| self.title_width(layout)) else {
+            return false;
+        };
+        text::display_width(&self.full_title(width).1) > width
+    }
 
-        Ok(())
+    /**
+        The song goes in the title style and whatever follows it in the artist style
+    **/
+    fn render_title(&self, frame : &mut Frame, x : u16, y : u16, song_part : &str, display_track : &str)
+    {
+        // a scrolled or cut off title only gets split when the song is still all there at the start
+        let split = if display_track.starts_with(song_part) { song_part.len() } else { display_track.len() };
+        let end = frame.set_string(x, y, &display_track[..split], self.theme.title);
+        frame.set_string(end, y, &display_track[split..], self.theme.artist);
     }
 
-    pub fn render_state(&self) -> Result<(), Box<dyn Error>>
+    /**
+        The controls with the song after them on one row, what the smaller
+        layouts have instead of the boxes
+    **/
+    fn render_mini_player(&self, frame : &mut Frame, rect : Rect)
     {
-        // get the total height of the terminal so we can display at the bottom
-        let (_, height) = terminal::size()?;
-        match self.state
+        let mut end = rect.x;
+        for (control, area) in Tui::inline_controls(rect)
         {
-            TuiState::CommandMode => {
-                // render command mode
-                stdout().execute(MoveTo(0, height - 1))?;
-                stdout().execute(Clear(ClearType::CurrentLine))?;
-                stdout().execute(Print("Command Mode"))?;
-            }
-            TuiState::NormalMode => {
-                // render normal mode
-                stdout().execute(MoveTo(0, height - 1))?;
-                stdout().execute(Clear(ClearType::CurrentLine))?;
-                stdout().execute(Print("Normal Mode"))?;
-            }
+            end = frame.set_string(area.x, area.y, &format!("[{}]", control.label()), self.control_style(control));
         }
 
-        stdout().flush()?;
-
-        Ok(())
+        let x = end + 1;
+        let (song_part, display_track) = self.playing_title(rect.right().saturating_sub(x) as usize);
+        self.render_title(frame, x, rect.y, &song_part, &display_track);
     }
 
+    /**
+        Where every control sits in the mini player, like control_boxes
+        but bracketed labels on a single row
+    **/
+    fn inline_controls(rect : Rect) -> Vec<(Control, Rect)>
+    {
+        let mut x = rect.x;
+        CONTROLS.into_iter().map(|control| {
+            let width = text::display_width(control.label()) as u16 + 2;
+            let area = Rect::new(x, rect.y, width, 1);
+            // a space between each one
+            x += width + 1;
+            (control, area)
+        }).collect()
+    }
 
-    #[allow(dead_code)]
-    pub async fn print_log(&self, log: &str) -> Result<(), Box<dyn Error>>
+    /**
+        How far along the song is as a bar with the times on either end
+    **/
+    fn render_progress(&self, frame : &mut Frame, rect : Rect)
     {
-        let (_, height) = terminal::size().expect("Error getting terminal size");
+        let (Some(current), Some(progress_ms)) = (&self.current, self.estimated_progress_ms()) else {
+            return;
+        };
+        let elapsed = views::format_duration(progress_ms);
+        let total = views::format_duration(current.duration_ms);
 
-        stdout().execute(MoveTo(0, height - 3)).expect("MoveTo failed");
-        stdout().execute(Clear(ClearType::CurrentLine)).expect("Clear failed");
-        stdout().execute(Print(log)).expect("Print failed");
-        stdout().flush().expect("Flush failed");
+        let bar_width = (rect.width as usize).saturating_sub(text::display_width(&elapsed) + text::display_width(&total) + 2);
+        let played = match current.duration_ms {
+            0 => 0,
+            duration_ms => (bar_width as u64 * progress_ms / duration_ms) as usize,
+        };
 
-        sleep(Duration::from_secs(5)).await; // Wait 5 seconds
+        let mut end = frame.set_string(rect.x, rect.y, &elapsed, self.theme.status);
+        end = frame.set_string(end + 1, rect.y, &"━".repeat(played), self.theme.progress);
+        end = frame.set_string(end, rect.y, &"─".repeat(bar_width - played), self.theme.border);
+        frame.set_string(end + 1, rect.y, &total, self.theme.status);
+    }
 
-        stdout().execute(MoveTo(0, height - 3)).expect("MoveTo failed");
-        stdout().execute(Clear(ClearType::CurrentLine)).expect("Clear failed");
-        stdout().flush().expect("Flush failed");
-        Ok(())
+    /**
+        Where the cover goes inside the album art pane, tucked into the top
+        right and kept square, None when there is no cover or no room for it
+    **/
+    fn art_rect(&self, pane : Rect) -> Option<Rect>
+    {
+        if !self.album_art.has_image() {
+            return None;
+        }
+
+        // cells are about twice as tall as they are wide so this keeps it square
+        let cols = pane.width.min(pane.height.saturating_mul(2)).min(40);
+        let rows = cols / 2;
+        if rows < 3 {
+            return None;
+        }
+        Some(Rect::new(pane.right().saturating_sub(cols), pane.y, cols, rows))
     }
 
-    pub async fn handle_input(&mut self) -> Result<(), Box<dyn Error>>
+    /**
+        Draws the detail view on top of the back stack, or the lyrics when the stack is empty
+    **/
+    pub fn render_view(&self, frame : &mut Frame, rect : Rect)
     {
-        if poll(Duration::from_millis(50))? 
-        {
-            let event = read()?;
-            match event
+        let width = rect.width;
+        let (top, bottom) = (rect.y, rect.bottom());
+
+        if bottom <= top + 1 {
+            return;
+        }
+
+        let Some(view_state) = self.view_stack.last() else {
+            if self.show_lyrics
             {
-                Event::Key(event) if event.kind == KeyEventKind::Press => {
-                    // handle key press
-
-                    // Control C will toggle the command mode and normal mode
-                    if event.modifiers == KeyModifiers::CONTROL && event.code == KeyCode::Char('c')
-                    {
-                        self.state = match self.state
-                        {
-                            TuiState::CommandMode => TuiState::NormalMode,
-                            TuiState::NormalMode => TuiState::CommandMode
-                        };
-                    }
+                self.render_lyrics(frame, rect);
+            }
+            return;
+        };
 
-                    // if we are in command mode
-                    if self.state == TuiState::CommandMode
-                    {
+        // the title takes the first line, the rest is the scrolling list
+        let visible_rows = bottom.saturating_sub(top + 1) as usize;
 
-                        // Replicating vim : command
-                        if event.code == KeyCode::Char(':')
-                        {
-                            self.handle_colon_command().await?;
-                        }
-                    }
-                    if self.state == TuiState::NormalMode
-                    {
-                        if self.state == TuiState::NormalMode {
-                            match event.code 
-                            {
-                                KeyCode::Char('p') | KeyCode::Char(' ') => {
-                                    self.control = Control::Pause;
-                                }
-                                KeyCode::Char('b') | KeyCode::Left => {
-                                    self.control = Control::SkipBack;
-                                }
-                                KeyCode::Char('f') | KeyCode::Right => {
-                                    self.control = Control::SkipForward;
-                                }
-                                KeyCode::Enter => {
-                                    // wanna print that we pressed enter
-                                    // Execute based on current selection
-                                    match self.control {
-                                        Control::Pause => {
-                                            self.auth.pause().await?;
-                                        }
-                                        Control::SkipBack => {
-                                            self.auth.skip_back().await?;
-                                        }
-                                        Control::SkipForward => {
-                                            self.auth.skip_forward().await?;
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-                _ => {}
+        let visual = self.mode.contains(Mode::Visual);
+        let hint = if visual { "q: queue, esc: cancel" } else { "o: open, backspace: back" };
+        let title = format!("{}  ({})", view_state.view.title(), hint);
+        frame.set_string(rect.x, top, &text::truncate(&title, width as usize), self.theme.title);
+
+        let range = view_state.range_from(self.visual_anchor);
+        for (i, row) in view_state.rows.iter().enumerate().skip(view_state.scroll).take(visible_rows)
+        {
+            let y = top + 1 + (i - view_state.scroll) as u16;
+            let mut style = if i == view_state.selected { self.theme.selected } else { Style::default() };
+            if visual && range.contains(&i) {
+                style = style.patch(self.theme.visual);
             }
+            frame.set_string(rect.x, y, &text::truncate(&row.text, width as usize), style);
         }
-        Ok(())
     }
 
-    pub async fn handle_colon_command(&mut self) -> Result<(), Box<dyn Error>>
+    /**
+        What is coming up next, one track per row under a title
+    **/
+    fn render_queue(&self, frame : &mut Frame, rect : Rect)
     {
-        let mut command = ":".to_string();
+        frame.set_string(rect.x, rect.y, &text::truncate("Up Next", rect.width as usize), self.theme.title);
 
-        // Render the initial `:` BEFORE entering the loop
-        let (_, height) = terminal::size()?;
-        stdout().execute(MoveTo(0, height - 1))?;
-        stdout().execute(Clear(ClearType::CurrentLine))?;
-        stdout().execute(Print(&command))?;
-        stdout().flush()?;
+        for (i, track) in self.queue.iter().take(rect.height.saturating_sub(1) as usize).enumerate()
+        {
+            let line = text::truncate(&format!("{} - {}", track.name, track.artists), rect.width as usize);
+            let y = rect.y + 1 + i as u16;
+            // the artists get their own style when the name made it in whole
+            let split = line.find(" - ").filter(|i| *i == track.name.len()).unwrap_or(line.len());
+            let end = frame.set_string(rect.x, y, &line[..split], Style::default());
+            frame.set_string(end, y, &line[split..], self.theme.artist);
+        }
+    }
 
-        loop
+    /**
+        Draws the lyrics between top and bottom, keeping the current line in
+        the middle of the pane and highlighted
+    **/
+    fn render_lyrics(&self, frame : &mut Frame, rect : Rect)
+    {
+        let (top, width) = (rect.y, rect.width);
+        let Some(lyrics) = &self.lyrics else {
+            return;
+        };
+
+        let current_line = self.current_lyric_line();
+        let visible_rows = rect.height as usize;
+        let first = match current_line {
+            Some(line) => line.saturating_sub(visible_rows / 2),
+            None => 0,
+        };
+
+        for (i, line) in lyrics.lines.iter().enumerate().skip(first).take(visible_rows)
         {
-            if poll(Duration::from_millis(50))?
-            {
-                if let Event::Key(key_event) = read()?
-                {
-                    // handle all key presses when typing with the : command
-                    match key_event.code
-                    {
-                        KeyCode::Esc => {
-                            break;
-                        },
-                        KeyCode::Enter => {
-                            self.handle_command(&command).await?;
-                            break;
-                        },
-                        KeyCode::Backspace => {
-                            command.pop();
-                        },
-                        KeyCode::Char(c) => {
-                            command.push(c);
-                        }
-                        _ => {}
-                    }
+            let line_text = text::truncate(&line.text, width as usize);
+            let x = rect.x + text::center_offset(&line_text, width as usize) as u16;
+            let style = if Some(i) == current_line { self.theme.selected } else { Style::default() };
+            frame.set_string(x, top + (i - first) as u16, &line_text, style);
+        }
+    }
 
-                    // render the command
-                    let (_, height) = terminal::size()?;
-                    stdout().execute(MoveTo(0, height - 1))?;
-                    stdout().execute(Clear(ClearType::CurrentLine))?;
-                    stdout().execute(Print(&command))?;
-                    stdout().flush()?;
-                }
-            }
+    /**
+        Where playback should be right now, the song is only polled every few
+        seconds so the time since the last poll gets added on while playing
+    **/
+    fn estimated_progress_ms(&self) -> Option<u64>
+    {
+        let current = self.current.as_ref()?;
+        let mut progress_ms = current.progress_ms;
+        if current.is_playing {
+            progress_ms += self.fetched_at.elapsed().as_millis() as u64;
         }
-        Ok(())
+        Some(progress_ms.min(current.duration_ms))
     }
 
-    pub async fn handle_command(&mut self, command: &str) -> Result<(), Box<dyn Error>>
+    fn current_lyric_line(&self) -> Option<usize>
     {
-        match command
-        {
-            ":q" => {
-                self.state = TuiState::NormalMode;
-                self.running = false;
-            },
-            ":print_token" => {
-                // get the height of the terminal
-                let (_, height) = terminal::size().expect("Error getting terminal size");
-                stdout().execute(MoveTo(0, height - 2)).expect("Error moving cursor");
-                stdout().execute(Clear(ClearType::CurrentLine)).expect("Error clearing line");
-                stdout().execute(Print(&self.auth.get_token().await?)).expect("Error printing token");
-            }
-            ":c" => {
-                // clear the terminal
-                stdout().execute(Clear(ClearType::All)).expect("Error clearing terminal");
+        self.lyrics.as_ref()?.line_at(self.estimated_progress_ms()?)
+    }
 
-                // set the song to none so that it detects a change
-                let mut last_track : MutexGuard<Option<String>>= LAST_TRACK.lock().unwrap();
-                *last_track = None;
+    /**
+        Looks the lyrics up again whenever the track changes, off in a task
+        since the providers can be slow
+    **/
+    fn update_lyrics(&mut self)
+    {
+        let key = match &self.current {
+            Some(track) if track.playing_type == PlayingType::Track => {
+                track.artists.first().map(|a| (a.name.clone(), track.song.clone()))
             }
-            _ => {}
+            _ => None,
+        };
+
+        if key == self.lyrics_key {
+            return;
+        }
+
+        self.lyrics = None;
+        self.lyrics_key = key.clone();
+        let Some(key) = key else {
+            return;
+        };
+
+        let manager = self.lyrics_manager.clone();
+        self.spawn(move |_, sender| async move {
+            let (artist, title) = key.clone();
+            // the providers block on files or the network so they get a thread of their own
+            let lyrics = tokio::task::spawn_blocking(move || manager.lookup(&artist, &title).map_err(|e| e.to_string()))
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            let _ = sender.send(AppMessage::Lyrics(key, lyrics));
+        });
+    }
+
+    /**
+        Stacks the notifications in the area given, newest at the bottom,
+        the oldest ones are left out when there are more than rows
+    **/
+    fn render_notifications(&self, frame : &mut Frame, area : Rect)
+    {
+        let visible : Vec<&Notification> = self.notifications.visible().collect();
+        let shown = &visible[visible.len().saturating_sub(area.height as usize)..];
+        for (i, notification) in shown.iter().enumerate()
+        {
+            self.render_notification(frame, notification, Rect::new(area.x, area.y + i as u16, area.width, 1));
         }
-        Ok(())
     }
+
+    /**
+        One notification in the row given, cut short if it doesnt fit
+    **/
+    fn render_notification(&self, frame : &mut Frame, notification : &Notification, rect : Rect)
+    {
+        let style = match notification.level
+        {
+            Level::Info => self.theme.status,
+            Level::Warn => self.theme.warning,
+            Level::Error => self.theme.error,
+        };
+        let line = format!(" {} ", text::truncate(&notification.display(), (rect.width as usize).saturating_sub(2)));
+        frame.set_string(rect.x, rect.y, &line, style);
+    }
+
+    pub fn render_state(&self, frame : &mut Frame, rect : Rect)
+    {
+        let (x, y) = (rect.x, rect.y);
+
+        // the search being typed takes over the state line
+        if self.mode.current() == Mode::Search {
+            let end = frame.set_string(x, y, "/", Style::default());
+            frame.set_string(end, y, self.search_line.text(), Style::default());
+
+            let cursor_x = x + 1 + self.search_line.cursor_width() as u16;
+            frame.cursor = Some((cursor_x.min(rect.right().saturating_sub(1)), y));
+            return;
+        }
+
+        // and so does the command
+        if self.mode.current() == Mode::Command {
+            let line = self.command_line.text();
+            let name_end = line.find(' ').unwrap_or(line.len());
+            let name = &line[..name_end];
+
+            // a name that cant become a command goes red while it is typed
+            let known = if name_end < line.len() {
+                commands::lookup(name).is_ok()
+            } else {
+                commands::could_be_command(name)
+            };
+            let name_style = if known { Style::default() } else { self.theme.error };
+
+            let mut end = frame.set_string(x, y, ":", Style::default());
+            end = frame.set_string(end, y, name, name_style);
+            frame.set_string(end, y, &line[name_end..], Style::default());
+
+            let cursor_x = x + 1 + self.command_line.cursor_width() as u16;
+            frame.cursor = Some((cursor_x.min(rect.right().saturating_sub(1)), y));
+            return;
+        }
+
+        // the right side goes first so the left gets cut off if they dont both fit
+        let right = self.segments(&self.status_bar.right);
+        let separator_width = text::display_width(SEGMENT_SEPARATOR);
+        let right_width : usize = right.iter().map(|(t, _)| text::display_width(t) + separator_width).sum();
+        let right_x = rect.right().saturating_sub(right_width as u16);
+        let mut end = right_x;
+        for (text, style) in &right
+        {
+            end = frame.set_string(end, y, SEGMENT_SEPARATOR, self.theme.border);
+            end = frame.set_string(end, y, text, *style);
+        }
+
+        // the start of a key sequence goes just before the right side like vim's showcmd
+        let pending = keymap::format_keys(&self.pending_keys);
+        let left_end = if pending.is_empty() {
+            right_x
+        } else {
+            let pending_x = right_x.saturating_sub(text::display_width(&pending) as u16 + 1);
+            frame.set_string(pending_x, y, &pending, self.theme.status);
+            pending_x.saturating_sub(1)
+        };
+
+        // with no rows to spare the newest notification takes the place of the left side
+        if let Some(notification) = self.notifications.latest().filter(|_| self.notification_rows(frame.height) == 0) {
+            self.render_notification(frame, notification, Rect::new(x, y, left_end.saturating_sub(x), 1));
+            return;
+        }
+
+        let mut end = x;
+        for (i, (text, style)) in self.segments(&self.status_bar.left).iter().enumerate()
+        {
+            let separator = if i == 0 { "" } else { SEGMENT_SEPARATOR };
+            let room = left_end.saturating_sub(end) as usize;
+            if room <= text::display_width(separator) {
+                break;
+            }
+            end = frame.set_string(end, y, separator, self.theme.border);
+            end = frame.set_string(end, y, &text::truncate(text, room - text::display_width(separator)), *style);
+        }
+    }
+
+    /**
+        The text and style of each segment, ones with nothing to show are left out
+    **/
+    fn segments(&self, segments : &[Segment]) -> Vec<(String, Style)>
+    {
+        let state = &self.player_state;
+        let has_device = state.device.is_some();
+        segments.iter().filter_map(|segment| match segment
+        {
+            Segment::Mode => Some((self.mode.current().indicator().to_string(), self.theme.status.bold())),
+            Segment::Device => Some((format!("🔈 {}", state.device.as_deref().unwrap_or("No device")), self.theme.status)),
+            Segment::Volume => state.volume.map(|v| (format!("🔊 {}%", v), self.theme.status)),
+            Segment::Shuffle if has_device => {
+                Some((format!("🔀 {}", if state.shuffle { "on" } else { "off" }), self.theme.status))
+            }
+            Segment::Repeat if has_device => Some((match state.repeat {
+                RepeatState::Off => "🔁 off",
+                RepeatState::Context => "🔁 all",
+                RepeatState::Track => "🔂 one",
+            }.to_string(), self.theme.status)),
+            Segment::Shuffle | Segment::Repeat => None,
+            Segment::Network => Some(match self.connection {
+                Connection::Offline => ("offline".to_string(), self.theme.warning),
+                Connection::RateLimited(until) if self.connection.is_limited() => {
+                    let left = until.saturating_duration_since(Instant::now()).as_secs() + 1;
+                    (format!("rate limited {}s", left), self.theme.warning)
+                }
+                _ => ("online".to_string(), self.theme.status),
+            }),
+            Segment::Profile => self.profile.as_ref().map(|p| (format!("👤 {}", p), self.theme.status)),
+            Segment::Clock => Some((status_bar::clock(), self.theme.status)),
+        }).collect()
+    }
+
+    /**
+        Whether something on screen changes by the second, the progress bar
+        of a playing song, the clock or a rate limit counting down
+    **/
+    fn needs_ticking(&self) -> bool
+    {
+        self.current.as_ref().is_some_and(|c| c.is_playing)
+            || self.status_bar.has(Segment::Clock)
+            || self.connection.is_limited()
+    }
+
+    /**
+        A box in the middle of the screen with a title in its top border,
+        the lines start at scroll and get cut off at the bottom
+    **/
+    fn render_overlay(&self, frame : &mut Frame, title : &str, lines : &[String], scroll : usize)
+    {
+        let widest = lines.iter().map(|l| text::display_width(l)).max().unwrap_or(0).max(text::display_width(title) + 2);
+        let width = (widest as u16 + 4).min(frame.width.saturating_sub(2));
+        let height = (lines.len() as u16 + 2).min(frame.height.saturating_sub(2));
+        if width < 4 || height < 3 {
+            return;
+        }
+
+        let left = (frame.width - width) / 2;
+        let top = (frame.height - height) / 2;
+        let (right, bottom) = (left + width - 1, top + height - 1);
+
+        for y in top..=bottom
+        {
+            for x in left..=right
+            {
+                let symbol = match (x, y) {
+                    (x, y) if x == left && y == top => "┌",
+                    (x, y) if x == right && y == top => "┐",
+                    (x, y) if x == left && y == bottom => "└",
+                    (x, y) if x == right && y == bottom => "┘",
+                    (_, y) if y == top || y == bottom => "─",
+                    (x, _) if x == left || x == right => "│",
+                    _ => " ",
+                };
+                frame.set_cell(x, y, symbol, self.theme.border);
+            }
+        }
+        frame.set_string(left + 2, top, &text::truncate(&format!(" {} ", title), width as usize - 4), self.theme.title);
+
+        let inner_width = width as usize - 4;
+        let visible = height as usize - 2;
+        for (i, line) in lines.iter().skip(scroll).take(visible).enumerate()
+        {
+            frame.set_string(left + 2, top + 1 + i as u16, &text::truncate(line, inner_width), Style::default());
+        }
+
+        // how far down we are when it doesnt all fit
+        if lines.len() > visible {
+            let position = format!(" {}/{} ", (scroll + visible).min(lines.len()), lines.len());
+            let x = (right + 1).saturating_sub(text::display_width(&position) as u16 + 2);
+            frame.set_string(x.max(left + 1), bottom, &position, self.theme.border);
+        }
+    }
+
+    /**
+        What the help overlay lists, the keys bound in every mode straight
+        from the keymap so remaps show up, then every command
+    **/
+    fn help_lines(&self) -> Vec<String>
+    {
+        let mut sections : Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for mode in KEYMAP_MODES
+        {
+            let rows = self.keymap.actions(mode).into_iter()
+                .map(|(action, keys)| (keys.join(" "), action.description().to_string()))
+                .collect();
+            sections.push((format!("{} mode", capitalize(mode.name())), rows));
+        }
+
+        // the line editor has its own keys that arent in the keymap
+        sections.push(("Command line and search".to_string(), vec![
+            ("<Tab> <S-Tab>".to_string(), "Complete".to_string()),
+            ("<Up> <Down>".to_string(), "History".to_string()),
+            ("<C-w> <C-u>".to_string(), "Delete a word, the line".to_string()),
+            ("<CR> <Esc>".to_string(), "Run, cancel".to_string()),
+        ]));
+
+        sections.push(("Commands".to_string(), commands::COMMANDS.iter().map(|c| (c.usage(), c.help.to_string())).collect()));
+
+        // one column width for the keys of every section so it all lines up
+        let key_width = sections.iter().flat_map(|(_, rows)| rows).map(|(keys, _)| text::display_width(keys)).max().unwrap_or(0);
+
+        let mut lines : Vec<String> = Vec::new();
+        for (title, rows) in sections
+        {
+            if rows.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(title);
+            for (keys, description) in rows
+            {
+                let padding = " ".repeat(key_width - text::display_width(&keys));
+                lines.push(format!("  {}{}  {}", keys, padding, description));
+            }
+        }
+        lines
+    }
+
+    fn handle_event(&mut self, event : Event)
+    {
+        match event
+        {
+            Event::Key(event) if event.kind == KeyEventKind::Press => {
+                // while a : command or a / search is being typed every key goes to it
+                match self.mode.current()
+                {
+                    Mode::Command => self.handle_command_key(&event),
+                    Mode::Search => self.handle_search_key(&event),
+                    _ => self.handle_key(event),
+                }
+            }
+            Event::Mouse(event) => {
+                self.handle_mouse(event);
+            }
+            Event::Resize(_, _) => {
+                self.handle_resize();
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Collects keys until they make up a binding in the current mode, a
+        key that leads nowhere drops what was collected and starts over with it
+    **/
+    fn handle_key(&mut self, event : KeyEvent)
+    {
+        self.pending_keys.push(KeyChord::from_event(&event));
+
+        match self.keymap.lookup(self.mode.current(), &self.pending_keys)
+        {
+            KeyLookup::Action(action) => {
+                self.pending_keys.clear();
+                self.run_action(action);
+            }
+            KeyLookup::Pending => {}
+            KeyLookup::None => {
+                let retry = self.pending_keys.len() > 1;
+                self.pending_keys.clear();
+                if retry {
+                    self.handle_key(event);
+                }
+            }
+        }
+    }
+
+    fn run_action(&mut self, action : Action)
+    {
+        match action
+        {
+            Action::Quit => self.running = false,
+            Action::Cancel => self.cancel(),
+            // Replicating vim : command
+            Action::CommandLine => self.open_command_line(),
+            Action::Search => self.open_search(),
+            Action::SearchNext => self.search_again(false),
+            Action::SearchPrevious => self.search_again(true),
+            Action::Visual => self.start_visual(),
+            Action::QueueSelection => self.queue_selection(),
+            Action::Help => {
+                self.help_scroll = 0;
+                self.enter_mode(Mode::Help);
+            }
+            // raw mode swallows the SIGTSTP the terminal would normally send
+            Action::Suspend => self.suspend(),
+            Action::SelectPause => self.select_control(Control::Pause),
+            Action::SelectSkipBack => self.select_control(Control::SkipBack),
+            Action::SelectSkipForward => self.select_control(Control::SkipForward),
+            Action::FocusNext => self.control = self.control.next(),
+            Action::FocusPrevious => self.control = self.control.previous(),
+            Action::Activate => self.run_control(),
+            Action::OpenAlbum => {
+                // for episodes the album is the show they belong to
+                let show_id = self.current.as_ref().and_then(|c| c.show.as_ref()).map(|s| s.id.clone());
+                if let Some(show_id) = show_id {
+                    self.open_target(ViewTarget::Show(show_id));
+                    return;
+                }
+
+                let album = self.current.as_ref().and_then(|c| Some((c.album_id.clone()?, c.album.clone())));
+                if let Some((album_id, album_name)) = album {
+                    info!("opening album {}", album_name);
+                    self.open_target(ViewTarget::Album(album_id));
+                }
+            }
+            Action::OpenArtist => {
+                // collaborations open the first credited artist
+                let artist_id = self.current.as_ref().and_then(|c| c.artists.first()).and_then(|a| a.id.clone());
+                if let Some(artist_id) = artist_id {
+                    self.open_target(ViewTarget::Artist(artist_id));
+                }
+            }
+            Action::PlayArtist => {
+                let artist = self.current.as_ref()
+                    .and_then(|c| c.artists.first())
+                    .and_then(|a| Some((a.uri.clone()?, a.name.clone())));
+                if let Some((uri, name)) = artist {
+                    self.spawn(move |auth, sender| async move {
+                        let result = auth.play_context(&uri).await;
+                        let _ = sender.send(AppMessage::playback(result.map(|()| format!("▶️ Playing {}", name)), "Playing the artist"));
+                    });
+                }
+            }
+            Action::SavedShows => self.open_target(ViewTarget::SavedShows),
+            Action::ToggleLyrics => self.show_lyrics = !self.show_lyrics,
+            Action::SeekBack => self.seek(-15_000),
+            Action::SeekForward => self.seek(15_000),
+            Action::SeekBackLong => self.seek(-30_000),
+            Action::SeekForwardLong => self.seek(30_000),
+            Action::VolumeUp => self.change_volume(VOLUME_STEP),
+            Action::VolumeDown => self.change_volume(-VOLUME_STEP),
+            Action::SelectNext | Action::SelectPrevious | Action::SelectFirst | Action::SelectLast if self.mode.current() == Mode::Help => {
+                self.scroll_help(action);
+            }
+            Action::SelectNext | Action::SelectPrevious | Action::SelectFirst | Action::SelectLast | Action::OpenSelected | Action::Back => {
+                self.run_view_action(action);
+            }
+        }
+    }
+
+    fn enter_mode(&mut self, mode : Mode)
+    {
+        self.pending_keys.clear();
+        self.mode.push(mode);
+    }
+
+    /**
+        Leaves a mode and whatever was opened on top of it, closing what
+        they had open
+    **/
+    fn leave_mode(&mut self, mode : Mode)
+    {
+        if !self.mode.contains(mode) {
+            return;
+        }
+        self.pending_keys.clear();
+        for left in self.mode.leave(mode)
+        {
+            self.close_mode(left);
+        }
+    }
+
+    /**
+        Esc, straight back to normal mode from wherever we are
+    **/
+    fn cancel(&mut self)
+    {
+        if self.mode.current() == Mode::Search {
+            self.restore_search_origin();
+        }
+        self.pending_keys.clear();
+        for left in self.mode.reset()
+        {
+            self.close_mode(left);
+        }
+    }
+
+    fn close_mode(&mut self, mode : Mode)
+    {
+        match mode
+        {
+            Mode::Command => self.command_line.close(),
+            Mode::Search => self.search_line.close(),
+            Mode::Popup => self.popup = None,
+            Mode::Normal | Mode::Visual | Mode::Help => {}
+        }
+    }
+
+    fn scroll_help(&mut self, action : Action)
+    {
+        let last = self.help_lines().len().saturating_sub(1);
+        self.help_scroll = match action
+        {
+            Action::SelectNext => (self.help_scroll + 1).min(last),
+            Action::SelectPrevious => self.help_scroll.saturating_sub(1),
+            Action::SelectFirst => 0,
+            Action::SelectLast => last,
+            _ => self.help_scroll,
+        };
+    }
+
+    /**
+        Visual mode, the selection becomes one end of a range of rows
+    **/
+    fn start_visual(&mut self)
+    {
+        let Some(view_state) = self.view_stack.last() else {
+            self.notify(Level::Error, "Open a view to select rows in".to_string());
+            return;
+        };
+        self.visual_anchor = view_state.selected;
+        self.enter_mode(Mode::Visual);
+    }
+
+    /**
+        Puts every track and episode in the visual selection on the queue,
+        in the order they are listed
+    **/
+    fn queue_selection(&mut self)
+    {
+        let Some(view_state) = self.view_stack.last() else {
+            return;
+        };
+        let uris : Vec<String> = view_state.range_from(self.visual_anchor)
+            .filter_map(|i| view_state.rows.get(i).and_then(|r| r.uri.clone()))
+            .collect();
+        self.leave_mode(Mode::Visual);
+
+        if uris.is_empty() {
+            self.notify(Level::Error, "Nothing in the selection can be queued".to_string());
+            return;
+        }
+
+        self.spawn(move |auth, sender| async move {
+            for uri in &uris
+            {
+                if let Err(e) = auth.add_to_queue(uri).await.map_err(|e| e.to_string()) {
+                    let _ = sender.send(AppMessage::Notify(Level::Error, format!("Queueing failed: {}", e)));
+                    return;
+                }
+            }
+            let _ = sender.send(AppMessage::PlaybackChanged(format!("Queued {} {}", uris.len(), if uris.len() == 1 { "item" } else { "items" })));
+        });
+    }
+
+    /**
+        Opens the / line, searching moves the selection of the open view
+        along as it is typed
+    **/
+    fn open_search(&mut self)
+    {
+        let Some(view_state) = self.view_stack.last() else {
+            self.notify(Level::Error, "Open a view to search in".to_string());
+            return;
+        };
+        self.search_origin = view_state.selected;
+        self.search_line.open();
+        self.enter_mode(Mode::Search);
+    }
+
+    fn handle_search_key(&mut self, event : &KeyEvent)
+    {
+        match self.search_line.handle_key(event)
+        {
+            EditResult::Editing => {
+                let query = self.search_line.text().to_string();
+                self.restore_search_origin();
+                if let Some(view_state) = self.view_stack.last_mut() {
+                    if let Some(found) = view_state.find(&query, self.search_origin, false) {
+                        view_state.select(found);
+                    }
+                }
+            }
+            EditResult::Cancel => self.cancel(),
+            EditResult::Submit(query) => {
+                if let Err(e) = self.search_line.save_history() {
+                    self.notify(Level::Warn, format!("Failed to save the search history: {}", e));
+                }
+                self.leave_mode(Mode::Search);
+                if query.is_empty() {
+                    return;
+                }
+
+                let found = self.view_stack.last().and_then(|v| v.find(&query, self.search_origin, false));
+                if found.is_none() {
+                    self.notify(Level::Error, format!("Pattern not found: {}", query));
+                }
+                self.last_search = Some(query);
+            }
+            // nothing to complete a search with
+            EditResult::Complete { .. } => {}
+        }
+    }
+
+    fn restore_search_origin(&mut self)
+    {
+        if let Some(view_state) = self.view_stack.last_mut() {
+            view_state.select(self.search_origin);
+        }
+    }
+
+    /**
+        n and N, the next match of the last search after the selection
+    **/
+    fn search_again(&mut self, backwards : bool)
+    {
+        let Some(query) = self.last_search.clone() else {
+            self.notify(Level::Error, "No previous search".to_string());
+            return;
+        };
+        let Some(view_state) = self.view_stack.last_mut() else {
+            return;
+        };
+
+        match view_state.find(&query, view_state.selected, backwards) {
+            Some(found) => {
+                view_state.select(found);
+            }
+            None => self.notify(Level::Error, format!("Pattern not found: {}", query)),
+        }
+    }
+
+    /**
+        The actions for moving around the detail views, they do nothing
+        while no view is open
+    **/
+    fn run_view_action(&mut self, action : Action)
+    {
+        let Some(view_state) = self.view_stack.last_mut() else {
+            return;
+        };
+
+        match action
+        {
+            Action::SelectNext => view_state.select_next(),
+            Action::SelectPrevious => view_state.select_previous(),
+            Action::SelectFirst => view_state.select_first(),
+            Action::SelectLast => view_state.select_last(),
+            Action::OpenSelected => {
+                if let Some(target) = view_state.selected_target() {
+                    self.open_target(target);
+                }
+            }
+            Action::Back => {
+                self.view_stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /**
+        Podcast style skipping, only while an episode is playing
+    **/
+    fn seek(&self, offset_ms : i64)
+    {
+        if !self.current.as_ref().is_some_and(|c| c.playing_type == PlayingType::Episode) {
+            return;
+        }
+        self.spawn(move |auth, sender| async move {
+            let result = auth.seek_relative(offset_ms).await;
+            let done = format!("{} {}s", if offset_ms < 0 { "⏪ Back" } else { "⏩ Forward" }, offset_ms.abs() / 1000);
+            let _ = sender.send(AppMessage::playback(result.map(|()| done), "Seek"));
+        });
+    }
+
+    /**
+        Moves the focus to a control, in direct mode it gets run right away too
+    **/
+    fn select_control(&mut self, control : Control)
+    {
+        self.control = control;
+        if self.control_mode == ControlMode::Direct {
+            self.run_control();
+        }
+    }
+
+    /**
+        Does whatever the selected control is for
+    **/
+    fn run_control(&self)
+    {
+        self.playback_control(self.control);
+    }
+
+    /**
+        Pauses or skips in the background, the controls and the commands both end up here
+    **/
+    fn playback_control(&self, control : Control)
+    {
+        match control {
+            Control::Pause => self.spawn(|auth, sender| async move {
+                let result = auth.pause().await;
+                let done = result.map(|paused| if paused { "⏸ Paused" } else { "▶️ Resumed" }.to_string());
+                let _ = sender.send(AppMessage::playback(done, "Pause"));
+            }),
+            Control::SkipBack => self.spawn(|auth, sender| async move {
+                let result = auth.skip_back().await;
+                let _ = sender.send(AppMessage::playback(result.map(|()| "⏮ Skipped back".to_string()), "Skip back"));
+            }),
+            Control::SkipForward => self.spawn(|auth, sender| async move {
+                let result = auth.skip_forward().await;
+                let _ = sender.send(AppMessage::playback(result.map(|()| "⏭ Skipped forward".to_string()), "Skip forward"));
+            }),
+        }
+    }
+
+    fn change_volume(&self, delta : i64)
+    {
+        self.spawn(move |auth, sender| async move {
+            let message = match auth.change_volume(delta).await {
+                Ok(volume) => AppMessage::PlaybackChanged(format!("🔊 Volume {}%", volume)),
+                Err(e) => AppMessage::Failed(format!("Volume failed: {}", e)),
+            };
+            let _ = sender.send(message);
+        });
+    }
+
+    /**
+        Clicks and the scroll wheel, whatever pane is under the pointer
+        in the last drawn layout decides what they do
+    **/
+    fn handle_mouse(&mut self, event : MouseEvent)
+    {
+        // an overlay covers the panes, clicking closes it and the wheel scrolls the help
+        if matches!(self.mode.current(), Mode::Help | Mode::Popup) {
+            match event.kind
+            {
+                MouseEventKind::Down(_) => self.cancel(),
+                MouseEventKind::ScrollDown if self.mode.current() == Mode::Help => self.scroll_help(Action::SelectNext),
+                MouseEventKind::ScrollUp if self.mode.current() == Mode::Help => self.scroll_help(Action::SelectPrevious),
+                _ => {}
+            }
+            return;
+        }
+
+        let (x, y) = (event.column, event.row);
+        let Some((pane, rect)) = self.rendered_layout.as_ref().and_then(|l| l.pane_at(x, y)) else {
+            return;
+        };
+
+        match (event.kind, pane)
+        {
+            (MouseEventKind::Down(MouseButton::Left), Pane::Controls) => {
+                if let Some((control, _)) = Tui::control_boxes(rect).into_iter().find(|(_, area)| area.contains(x, y)) {
+                    self.control = control;
+                    self.run_control();
+                }
+            }
+            (MouseEventKind::Down(MouseButton::Left), Pane::MiniPlayer) => {
+                if let Some((control, _)) = Tui::inline_controls(rect).into_iter().find(|(_, area)| area.contains(x, y)) {
+                    self.control = control;
+                    self.run_control();
+                }
+            }
+            (MouseEventKind::Down(MouseButton::Left), Pane::Library) => {
+                let Some(view_state) = self.view_stack.last_mut() else {
+                    return;
+                };
+                // the first row of the pane is the title
+                let Some(row) = (y - rect.y).checked_sub(1) else {
+                    return;
+                };
+                let index = view_state.scroll + row as usize;
+
+                // clicking the row that is already selected opens it
+                if index == view_state.selected {
+                    if let Some(target) = view_state.selected_target() {
+                        self.open_target(target);
+                    }
+                } else {
+                    view_state.select(index);
+                }
+            }
+            // the wheel scrolls an open list and turns the volume everywhere else
+            (MouseEventKind::ScrollDown, Pane::Library) if !self.view_stack.is_empty() => {
+                if let Some(view_state) = self.view_stack.last_mut() {
+                    view_state.select_next();
+                }
+            }
+            (MouseEventKind::ScrollUp, Pane::Library) if !self.view_stack.is_empty() => {
+                if let Some(view_state) = self.view_stack.last_mut() {
+                    view_state.select_previous();
+                }
+            }
+            (MouseEventKind::ScrollUp, _) => self.change_volume(VOLUME_STEP),
+            (MouseEventKind::ScrollDown, _) => self.change_volume(-VOLUME_STEP),
+            _ => {}
+        }
+    }
+
+    /**
+        Fetches whatever the target points at in the background, it gets
+        pushed on the back stack once it arrives
+    **/
+    fn open_target(&mut self, target : ViewTarget)
+    {
+        if self.view_stack.last().is_some_and(|v| v.view.shows(&target)) {
+            return;
+        }
+
+        self.spawn(move |auth, sender| async move {
+            let view = match &target
+            {
+                ViewTarget::Album(id) => auth.get_album(id).await.map(View::Album),
+                ViewTarget::Artist(id) => auth.get_artist(id).await.map(View::Artist),
+                ViewTarget::SavedShows => auth.get_saved_shows().await.map(View::SavedShows),
+                ViewTarget::Show(id) => auth.get_show(id).await.map(|(show, episodes)| View::Show(show, episodes)),
+                ViewTarget::Episode { uri, position_ms } => {
+                    let result = auth.play_uri(uri, *position_ms).await;
+                    let _ = sender.send(AppMessage::playback(result.map(|()| "▶️ Playing the episode".to_string()), "Playing the episode"));
+                    return;
+                }
+            };
+            let _ = sender.send(AppMessage::ViewLoaded(target, view.map_err(|e| e.to_string())));
+        });
+    }
+
+    /**
+        Opens the : line and refreshes what its arguments complete to once
+        they are old enough, devices come and go so they go stale sooner
+    **/
+    fn open_command_line(&mut self)
+    {
+        self.command_line.open();
+        self.enter_mode(Mode::Command);
+
+        if is_stale(self.devices_fetched, DEVICES_TTL) {
+            self.devices_fetched = Some(Instant::now());
+            self.spawn(|auth, sender| async move {
+                let devices = auth.get_devices().await.map_err(|e| e.to_string());
+                let _ = sender.send(AppMessage::Devices(devices));
+            });
+        }
+        if is_stale(self.playlists_fetched, PLAYLISTS_TTL) {
+            self.playlists_fetched = Some(Instant::now());
+            self.spawn(|auth, sender| async move {
+                let playlists = auth.get_playlists().await.map_err(|e| e.to_string());
+                let _ = sender.send(AppMessage::Playlists(playlists));
+            });
+        }
+    }
+
+    /**
+        Typing after a :, enter runs it and esc throws it away
+    **/
+    fn handle_command_key(&mut self, event : &KeyEvent)
+    {
+        match self.command_line.handle_key(event)
+        {
+            EditResult::Editing => {}
+            EditResult::Cancel => self.cancel(),
+            EditResult::Submit(line) => {
+                self.leave_mode(Mode::Command);
+                if let Err(e) = self.command_line.save_history() {
+                    self.notify(Level::Warn, format!("Failed to save the command history: {}", e));
+                }
+                self.handle_command(&line);
+            }
+            EditResult::Complete { backwards } => {
+                if self.command_line.is_completing() {
+                    self.command_line.cycle_completion(backwards);
+                } else {
+                    let (start, candidates) = self.completions(self.command_line.before_cursor());
+                    self.command_line.start_completion(start, candidates, backwards);
+                }
+            }
+        }
+    }
+
+    /**
+        What the text left of the cursor could be finished with, along with
+        where the part being completed starts
+    **/
+    fn completions(&self, before : &str) -> (usize, Vec<String>)
+    {
+        let matching = |names : Vec<&str>, typed : &str| -> Vec<String> {
+            let typed = typed.to_lowercase();
+            names.into_iter().filter(|n| n.to_lowercase().starts_with(&typed)).map(|n| n.to_string()).collect()
+        };
+
+        let Some(name_end) = before.find(' ') else {
+            let names = commands::COMMANDS.iter().map(|c| c.name).collect();
+            return (0, matching(names, before));
+        };
+        let Ok(command) = commands::lookup(&before[..name_end]) else {
+            return (0, Vec::new());
+        };
+
+        let start = before.rfind(' ').map(|i| i + 1).unwrap_or(0);
+        let previous : Vec<&str> = before[name_end..start].split_whitespace().collect();
+        let Some(arg) = command.arg_at(&previous) else {
+            return (start, Vec::new());
+        };
+
+        // names with spaces in them take the whole rest of the line
+        let start = if arg.rest {
+            let rest = &before[name_end..];
+            name_end + rest.len() - rest.trim_start().len()
+        } else {
+            start
+        };
+
+        let names : Vec<&str> = match arg.kind
+        {
+            ArgKind::Mode => KEYMAP_MODES.iter().map(|m| m.name()).collect(),
+            ArgKind::Action => keymap::action_names().collect(),
+            ArgKind::Command => commands::COMMANDS.iter().map(|c| c.name).collect(),
+            ArgKind::Device => self.devices.iter().map(|d| d.name.as_str()).collect(),
+            ArgKind::Playlist => self.playlists.iter().map(|p| p.name.as_str()).collect(),
+            ArgKind::File => return (start, file_completions(&before[start..])),
+            ArgKind::Theme => theme::preset_names().collect(),
+            ArgKind::Keys | ArgKind::Volume => Vec::new(),
+        };
+        (start, matching(names, &before[start..]))
+    }
+
+    /**
+        :device name, moves playback over to the device with that name
+    **/
+    fn device_command(&self, name : &str)
+    {
+        let name = name.to_string();
+        self.spawn(move |auth, sender| async move {
+            let devices = match auth.get_devices().await.map_err(|e| e.to_string()) {
+                Ok(devices) => devices,
+                Err(e) => {
+                    let _ = sender.send(AppMessage::Failed(format!("Failed to get devices: {}", e)));
+                    return;
+                }
+            };
+
+            let Some(device) = find_by_name(&devices, &name, |d| &d.name) else {
+                let _ = sender.send(AppMessage::Notify(Level::Error, format!("No device called {}", name)));
+                return;
+            };
+            let result = auth.transfer_playback(&device.id).await;
+            let _ = sender.send(AppMessage::playback(result.map(|()| format!("🔈 Playing on {}", device.name)), "Switching device"));
+        });
+    }
+
+    /**
+        :playlist name, starts playing one of the user's playlists
+    **/
+    fn playlist_command(&self, name : &str)
+    {
+        let name = name.to_string();
+        self.spawn(move |auth, sender| async move {
+            let playlists = match auth.get_playlists().await.map_err(|e| e.to_string()) {
+                Ok(playlists) => playlists,
+                Err(e) => {
+                    let _ = sender.send(AppMessage::Failed(format!("Failed to get playlists: {}", e)));
+                    return;
+                }
+            };
+
+            let Some(playlist) = find_by_name(&playlists, &name, |p| &p.name) else {
+                let _ = sender.send(AppMessage::Notify(Level::Error, format!("No playlist called {}", name)));
+                return;
+            };
+            let result = auth.play_context(&playlist.uri).await;
+            let _ = sender.send(AppMessage::playback(result.map(|()| format!("▶️ Playing {}", playlist.name)), "Playing the playlist"));
+        });
+    }
+
+    /**
+        Runs a line typed after a :, what it says and anything that goes wrong end up in a notification
+    **/
+    pub fn handle_command(&mut self, line : &str)
+    {
+        match self.run_line(line) {
+            Ok(Some(message)) => self.notify(Level::Info, message),
+            Ok(None) => {}
+            Err(e) => self.notify(Level::Error, e),
+        }
+    }
+
+    fn run_line(&mut self, line : &str) -> Result<Option<String>, String>
+    {
+        match commands::parse(line)? {
+            Some(command) => self.run_command(command),
+            None => Ok(None),
+        }
+    }
+
+    /**
+        Runs every line of a command file, blank lines and ones starting
+        with " or # are skipped, a bad line doesnt stop the ones after it
+        and comes back as file:line: error
+    **/
+    fn source_file(&mut self, path : &Path) -> Result<Vec<String>, String>
+    {
+        if self.source_depth >= SOURCE_DEPTH_LIMIT {
+            return Err(format!("{}: files source each other too deep", path.display()));
+        }
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        self.source_depth += 1;
+        let previous_file = self.sourcing.replace(path.to_path_buf());
+
+        let mut errors : Vec<String> = Vec::new();
+        for (number, line) in contents.lines().enumerate()
+        {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('"') || line.starts_with('#') {
+                continue;
+            }
+            let location = format!("{}:{}", path.display(), number + 1);
+            let previous_location = self.source_location.replace(location.clone());
+            // messages like the ones :map gives are only noise here
+            if let Err(e) = self.run_line(line) {
+                errors.push(format!("{}: {}", location, e));
+            }
+            self.source_location = previous_location;
+        }
+
+        self.sourcing = previous_file;
+        self.source_depth -= 1;
+        Ok(errors)
+    }
+
+    /**
+        :source file, relative paths in a sourced file start from its folder
+    **/
+    fn source_command(&mut self, file : &str) -> Result<Option<String>, String>
+    {
+        let base = self.sourcing.as_ref().and_then(|p| p.parent()).map(|p| p.to_path_buf());
+        let path = paths::resolve(file, base.as_deref());
+
+        let errors = self.source_file(&path)?;
+        // a nested :source reports its errors through the file sourcing it
+        if self.sourcing.is_some() {
+            if errors.is_empty() {
+                return Ok(None);
+            }
+            return Err(errors.join("; "));
+        }
+        self.report_source_errors(&path, errors);
+        Ok(None)
+    }
+
+    /**
+        One bad line fits in a notification, more than that get a popup
+    **/
+    fn report_source_errors(&mut self, path : &Path, errors : Vec<String>)
+    {
+        match errors.len()
+        {
+            0 => self.notify(Level::Info, format!("Sourced {}", path.display())),
+            1 => self.notify(Level::Error, errors[0].clone()),
+            count => {
+                self.popup = Some((format!("{} errors in {}", count, path.display()), errors.join("\n")));
+                self.enter_mode(Mode::Popup);
+            }
+        }
+    }
+
+    /**
+        Runs the comfyfyrc at startup, not having one is fine
+    **/
+    fn source_rc(&mut self)
+    {
+        let path = Config::rc_path();
+        if !path.exists() {
+            return;
+        }
+        match self.source_file(&path) {
+            Ok(errors) if errors.is_empty() => {}
+            Ok(errors) => self.report_source_errors(&path, errors),
+            Err(e) => self.notify(Level::Error, e),
+        }
+    }
+
+    /**
+        Does what a parsed command asks for, commands that answer straight
+        away return their message and the rest report back once they are done
+    **/
+    fn run_command(&mut self, command : ParsedCommand) -> Result<Option<String>, String>
+    {
+        match command.spec.id
+        {
+            CommandId::Quit => self.running = false,
+            CommandId::Clear => {
+                // clear the terminal, the next draw puts everything back
+                self.notifications.clear();
+                self.renderer.invalidate();
+            }
+            CommandId::PrintToken => {
+                self.spawn(|auth, sender| async move {
+                    let message = match auth.get_token().await {
+                        Ok(token) => AppMessage::Popup("Access token".to_string(), token),
+                        Err(e) => AppMessage::Failed(format!("Failed to get the token: {}", e)),
+                    };
+                    let _ = sender.send(message);
+                });
+            }
+            CommandId::Pause => self.playback_control(Control::Pause),
+            CommandId::Next => self.playback_control(Control::SkipForward),
+            CommandId::Previous => self.playback_control(Control::SkipBack),
+            CommandId::Volume => self.volume_command(command.get("percent").unwrap_or_default())?,
+            CommandId::Device => self.device_command(command.get("name").unwrap_or_default()),
+            CommandId::Playlist => self.playlist_command(command.get("name").unwrap_or_default()),
+            CommandId::Map => return self.map_command(&command).map(Some),
+            CommandId::Unmap => return self.unmap_command(&command).map(Some),
+            CommandId::Source => return self.source_command(command.get("file").unwrap_or_default()),
+            CommandId::Theme => {
+                let name = command.get("preset").unwrap_or_default();
+                let preset = Preset::from_name(name).ok_or_else(|| {
+                    format!("unknown theme \"{}\", the themes are {}", name, theme::preset_names().collect::<Vec<_>>().join(", "))
+                })?;
+                self.theme = self.theme_config.with_preset(preset, self.color_depth)?;
+            }
+            CommandId::Help => match command.get("command") {
+                Some(name) => return help_command(name).map(Some),
+                None => {
+                    self.help_scroll = 0;
+                    self.enter_mode(Mode::Help);
+                }
+            },
+        }
+        Ok(None)
+    }
+
+    /**
+        :volume 40 sets it, :volume +10 and :volume -10 turn it up and down
+    **/
+    fn volume_command(&self, percent : &str) -> Result<(), String>
+    {
+        let invalid = || format!("volume wants a percent like 40, +10 or -10, not \"{}\"", percent);
+
+        if percent.starts_with('+') || percent.starts_with('-') {
+            let delta : i64 = percent.parse().map_err(|_| invalid())?;
+            self.change_volume(delta);
+            return Ok(());
+        }
+
+        let volume : u64 = percent.parse().map_err(|_| invalid())?;
+        if volume > 100 {
+            return Err(invalid());
+        }
+        self.spawn(move |auth, sender| async move {
+            let message = match auth.set_volume(volume).await {
+                Ok(()) => AppMessage::PlaybackChanged(format!("🔊 Volume {}%", volume)),
+                Err(e) => AppMessage::Failed(format!("Volume failed: {}", e)),
+            };
+            let _ = sender.send(message);
+        });
+        Ok(())
+    }
+
+    /**
+        :map [mode] keys action, the mode is normal when it is left out
+    **/
+    fn map_command(&mut self, command : &ParsedCommand) -> Result<String, String>
+    {
+        let mode = command_mode(command)?;
+        let keys = command.get("keys").unwrap_or_default();
+        let action = command.get("action").unwrap_or_default();
+
+        let chords = keymap::parse_keys(keys)?;
+        let action = Action::from_name(action).ok_or_else(|| format!("unknown action \"{}\"", action))?;
+        self.keymap.bind(mode, chords.clone(), action)?;
+        Ok(format!("{} mode {} is now {}", mode.name(), keymap::format_keys(&chords), action.name()))
+    }
+
+    /**
+        :unmap [mode] keys
+    **/
+    fn unmap_command(&mut self, command : &ParsedCommand) -> Result<String, String>
+    {
+        let mode = command_mode(command)?;
+        let keys = command.get("keys").unwrap_or_default();
+
+        let chords = keymap::parse_keys(keys)?;
+        self.keymap.unbind(mode, &chords)?;
+        Ok(format!("{} mode {} is unmapped", mode.name(), keymap::format_keys(&chords)))
+    }
+}
+
+/**
+        The mode a :map or :unmap is for, normal when it was left out
+**/
+fn command_mode(command : &ParsedCommand) -> Result<Mode, String>
+{
+    match command.get("mode") {
+        Some(mode) => Mode::from_keymap_name(mode).ok_or_else(|| format!("unknown mode \"{}\", keys can be bound in {}", mode, keymap::keymap_mode_names())),
+        None => Ok(Mode::Normal),
+    }
+}
+
+/**
+        :help name, how to use that command
+**/
+fn help_command(name : &str) -> Result<String, String>
+{
+    let command = commands::lookup(name.trim_start_matches(':'))?;
+    let mut help = format!("{}  {}", command.usage(), command.help);
+    if !command.aliases.is_empty() {
+        help.push_str(&format!(" (also :{})", command.aliases.join(", :")));
+    }
+    Ok(help)
+}
+
+/**
+        First letter upper case, for section titles
+**/
+fn capitalize(text : &str) -> String
+{
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/**
+        Files and folders starting with what was typed, folders get a / on
+        the end so tab can carry on into them
+**/
+fn file_completions(typed : &str) -> Vec<String>
+{
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(slash) => (&typed[..=slash], &typed[slash + 1..]),
+        None => ("", typed),
+    };
+    let Ok(entries) = fs::read_dir(paths::resolve(if dir.is_empty() { "." } else { dir }, None)) else {
+        return Vec::new();
+    };
+
+    let mut names : Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            // hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if e.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/**
+        Matches what was typed against names ignoring case, an exact match
+        wins and otherwise the first name starting with it
+**/
+fn find_by_name<'a, T>(items : &'a [T], name : &str, name_of : impl Fn(&T) -> &str) -> Option<&'a T>
+{
+    let name = name.to_lowercase();
+    items.iter().find(|i| name_of(i).to_lowercase() == name)
+        .or_else(|| items.iter().find(|i| name_of(i).to_lowercase().starts_with(&name)))
+}
+
+/**
+        True when something was never fetched or was fetched longer than ttl ago
+**/
+fn is_stale(fetched : Option<Instant>, ttl : Duration) -> bool
+{
+    fetched.is_none_or(|fetched| fetched.elapsed() >= ttl)
 }
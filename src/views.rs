@@ -0,0 +1,318 @@
+use super::spotify_client_auth::{
+    SpotifyAlbum,
+    SpotifyArtist,
+    SpotifyEpisode,
+    SpotifyShow,
+    SpotifyTrack
+};
+
+/*
+
+Detail views that get pushed on top of the now playing screen, each
+view is turned into a flat list of rows so the tui only has to know how
+to draw and scroll a list
+
+**/
+
+pub enum View
+{
+    Album(SpotifyAlbum),
+    Artist(SpotifyArtist),
+    SavedShows(Vec<SpotifyShow>),
+    Show(SpotifyShow, Vec<SpotifyEpisode>)
+}
+
+/**
+        Where a row leads to when it gets opened
+**/
+#[derive(Clone)]
+pub enum ViewTarget
+{
+    Album(String),
+    Artist(String),
+    SavedShows,
+    Show(String),
+    // episodes dont get a view, opening one plays it from its resume point
+    Episode { uri : String, position_ms : u64 }
+}
+
+pub struct ViewRow
+{
+    pub text : String,
+    pub target : Option<ViewTarget>,
+    pub selectable : bool,
+    // what goes on the queue when the row is picked in visual mode
+    pub uri : Option<String>,
+}
+
+impl ViewRow
+{
+    fn heading(text : String) -> Self
+    {
+        ViewRow { text, target : None, selectable : false, uri : None }
+    }
+
+    fn item(text : String, target : Option<ViewTarget>) -> Self
+    {
+        ViewRow { text, target, selectable : true, uri : None }
+    }
+
+    fn with_uri(mut self, uri : Option<String>) -> Self
+    {
+        self.uri = uri;
+        self
+    }
+}
+
+/**
+        A view on the back stack, remembers where the selection was so
+        going back lands you where you left off
+**/
+pub struct ViewState
+{
+    pub view : View,
+    pub rows : Vec<ViewRow>,
+    pub selected : usize,
+    pub scroll : usize,
+}
+
+impl ViewState
+{
+    pub fn new(view : View) -> Self
+    {
+        let rows = view.rows();
+        let selected = rows.iter().position(|r| r.selectable).unwrap_or(0);
+        ViewState { view, rows, selected, scroll : 0 }
+    }
+
+    pub fn select_next(&mut self)
+    {
+        if let Some(next) = (self.selected + 1..self.rows.len()).find(|i| self.rows[*i].selectable) {
+            self.selected = next;
+        }
+    }
+
+    pub fn select_previous(&mut self)
+    {
+        if let Some(previous) = (0..self.selected).rev().find(|i| self.rows[*i].selectable) {
+            self.selected = previous;
+        }
+    }
+
+    pub fn select_first(&mut self)
+    {
+        if let Some(first) = self.rows.iter().position(|r| r.selectable) {
+            self.selected = first;
+        }
+    }
+
+    pub fn select_last(&mut self)
+    {
+        if let Some(last) = self.rows.iter().rposition(|r| r.selectable) {
+            self.selected = last;
+        }
+    }
+
+    /**
+        Selects a row by index, headings and rows past the end are ignored
+    **/
+    pub fn select(&mut self, index : usize) -> bool
+    {
+        if self.rows.get(index).is_some_and(|r| r.selectable) {
+            self.selected = index;
+            return true;
+        }
+        false
+    }
+
+    /**
+        The next selectable row containing query after from, going round
+        past the end, the case is ignored
+    **/
+    pub fn find(&self, query : &str, from : usize, backwards : bool) -> Option<usize>
+    {
+        let query = query.to_lowercase();
+        let count = self.rows.len();
+        if query.is_empty() || count == 0 {
+            return None;
+        }
+
+        (1..=count)
+            .map(|step| if backwards { (from + count * 2 - step) % count } else { (from + step) % count })
+            .find(|i| self.rows[*i].selectable && self.rows[*i].text.to_lowercase().contains(&query))
+    }
+
+    /**
+        The rows from the anchor to the selection, whichever way round they are
+    **/
+    pub fn range_from(&self, anchor : usize) -> std::ops::RangeInclusive<usize>
+    {
+        anchor.min(self.selected)..=anchor.max(self.selected)
+    }
+
+    pub fn selected_target(&self) -> Option<ViewTarget>
+    {
+        self.rows.get(self.selected).and_then(|r| r.target.clone())
+    }
+
+    /**
+        Moves the scroll offset just enough to keep the selection on screen
+    **/
+    pub fn scroll_to_selection(&mut self, visible_rows : usize)
+    {
+        if visible_rows == 0 {
+            return;
+        }
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible_rows {
+            self.scroll = self.selected + 1 - visible_rows;
+        }
+    }
+}
+
+pub fn format_duration(duration_ms : u64) -> String
+{
+    let total_seconds = duration_ms / 1000;
+    format!("{}:{:02}", total_seconds / 60, total_seconds % 60)
+}
+
+fn track_line(index : usize, track : &SpotifyTrack) -> String
+{
+    format!("{:>3}. {} - {}  {}", index, track.name, track.artists, format_duration(track.duration_ms))
+}
+
+fn release_year(release_date : &str) -> &str
+{
+    release_date.split('-').next().unwrap_or("")
+}
+
+impl View
+{
+    /**
+        True when this view already shows what the target points at
+    **/
+    pub fn shows(&self, target : &ViewTarget) -> bool
+    {
+        match (self, target)
+        {
+            (View::Album(album), ViewTarget::Album(id)) => album.id == *id,
+            (View::Artist(artist), ViewTarget::Artist(id)) => artist.id == *id,
+            (View::SavedShows(_), ViewTarget::SavedShows) => true,
+            (View::Show(show, _), ViewTarget::Show(id)) => show.id == *id,
+            _ => false,
+        }
+    }
+
+    pub fn title(&self) -> String
+    {
+        match self
+        {
+            View::Album(album) => format!("Album: {}", album.name),
+            View::Artist(artist) => format!("Artist: {}", artist.name),
+            View::SavedShows(_) => "Saved Shows".to_string(),
+            View::Show(show, _) => format!("Show: {}", show.name),
+        }
+    }
+
+    pub fn rows(&self) -> Vec<ViewRow>
+    {
+        let mut rows : Vec<ViewRow> = Vec::new();
+        match self
+        {
+            View::Album(album) => {
+                let total_ms : u64 = album.tracks.iter().map(|t| t.duration_ms).sum();
+                rows.push(ViewRow::heading(format!(
+                    "{} ({}) - {} tracks, {}",
+                    album.artists,
+                    release_year(&album.release_date),
+                    album.tracks.len(),
+                    format_duration(total_ms)
+                )));
+                rows.push(ViewRow::heading(String::new()));
+                for track in &album.tracks
+                {
+                    rows.push(ViewRow::item(track_line(track.track_number as usize, track), None).with_uri(track.uri.clone()));
+                }
+            }
+            View::Artist(artist) => {
+                let mut summary = format!("{} followers", artist.followers);
+                if !artist.genres.is_empty() {
+                    summary = format!("{} - {}", artist.genres.join(", "), summary);
+                }
+                rows.push(ViewRow::heading(summary));
+
+                if !artist.top_tracks.is_empty() {
+                    rows.push(ViewRow::heading(String::new()));
+                    rows.push(ViewRow::heading("Top Tracks".to_string()));
+                    for (i, track) in artist.top_tracks.iter().enumerate()
+                    {
+                        rows.push(ViewRow::item(track_line(i + 1, track), None).with_uri(track.uri.clone()));
+                    }
+                }
+
+                for (heading, releases) in [("Albums", &artist.albums), ("Singles", &artist.singles)]
+                {
+                    if releases.is_empty() {
+                        continue;
+                    }
+                    rows.push(ViewRow::heading(String::new()));
+                    rows.push(ViewRow::heading(heading.to_string()));
+                    for release in releases
+                    {
+                        rows.push(ViewRow::item(
+                            format!("     {} ({})", release.name, release_year(&release.release_date)),
+                            Some(ViewTarget::Album(release.id.clone()))
+                        ));
+                    }
+                }
+
+                if !artist.related.is_empty() {
+                    rows.push(ViewRow::heading(String::new()));
+                    rows.push(ViewRow::heading("Related Artists".to_string()));
+                    for related in &artist.related
+                    {
+                        rows.push(ViewRow::item(
+                            format!("     {}", related.name),
+                            Some(ViewTarget::Artist(related.id.clone()))
+                        ));
+                    }
+                }
+            }
+            View::SavedShows(shows) => {
+                if shows.is_empty() {
+                    rows.push(ViewRow::heading("No saved shows".to_string()));
+                }
+                for show in shows
+                {
+                    rows.push(ViewRow::item(
+                        format!("{} - {} ({} episodes)", show.name, show.publisher, show.total_episodes),
+                        Some(ViewTarget::Show(show.id.clone()))
+                    ));
+                }
+            }
+            View::Show(show, episodes) => {
+                rows.push(ViewRow::heading(format!("{} - {} episodes", show.publisher, show.total_episodes)));
+                rows.push(ViewRow::heading(String::new()));
+                for episode in episodes
+                {
+                    let progress = if episode.fully_played {
+                        "played".to_string()
+                    } else if episode.resume_position_ms > 0 {
+                        format!("{} / {}", format_duration(episode.resume_position_ms), format_duration(episode.duration_ms))
+                    } else {
+                        format_duration(episode.duration_ms)
+                    };
+
+                    // a fully played episode starts again from the top
+                    let position_ms = if episode.fully_played { 0 } else { episode.resume_position_ms };
+                    rows.push(ViewRow::item(
+                        format!("{}  {}  {}", episode.release_date, episode.name, progress),
+                        Some(ViewTarget::Episode { uri : episode.uri.clone(), position_ms })
+                    ).with_uri(Some(episode.uri.clone())));
+                }
+            }
+        }
+        rows
+    }
+}